@@ -0,0 +1,58 @@
+//! Compares generating snowflakes with and without an event history.
+//!
+//! Run with `cargo bench --bench event_history`.
+
+use ferrischat_snowflake_generator::{SnowflakeGenerator, FERRIS_EPOCH};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// How many snowflakes each thread generates per measurement.
+const ITERATIONS: u64 = 2_000_000;
+
+/// Builds a generator whose clock moves on every 1,024 reads, so the counter never runs out and
+/// the system clock doesn't add noise.
+fn generator(history: Option<usize>) -> SnowflakeGenerator {
+    let reads = AtomicU64::new(0);
+    let generator = SnowflakeGenerator::new(0)
+        .with_clock(move || FERRIS_EPOCH + (reads.fetch_add(1, Ordering::Relaxed) >> 10) as u128);
+    match history {
+        Some(capacity) => generator.with_event_history(capacity),
+        None => generator,
+    }
+}
+
+/// Returns how many nanoseconds one snowflake takes to generate on average, with `threads`
+/// threads generating from the same generator at once.
+fn measure(history: Option<usize>, threads: u64) -> f64 {
+    let generator = Arc::new(generator(history));
+    let start = Instant::now();
+    let workers: Vec<_> = (0..threads)
+        .map(|_| {
+            let generator = Arc::clone(&generator);
+            std::thread::spawn(move || {
+                for _ in 0..ITERATIONS {
+                    black_box(generator.generate::<0>(0));
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    start.elapsed().as_nanos() as f64 / (ITERATIONS * threads) as f64
+}
+
+fn main() {
+    for threads in [1, 4] {
+        for (name, history) in [("no history", None), ("history of 1,024", Some(1_024))] {
+            println!(
+                "{} thread(s), {}: {:.1}ns per snowflake",
+                threads,
+                name,
+                measure(history, threads)
+            );
+        }
+    }
+}
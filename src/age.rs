@@ -0,0 +1,89 @@
+use crate::{get_epoch_time, timestamp_of};
+
+/// The units [`relative_time_of`] rounds to, in milliseconds, from largest to smallest.
+const UNITS: [(u128, &str); 5] = [
+    (365 * 24 * 60 * 60 * 1_000, "year"),
+    (24 * 60 * 60 * 1_000, "day"),
+    (60 * 60 * 1_000, "hour"),
+    (60 * 1_000, "minute"),
+    (1_000, "second"),
+];
+
+/// Returns how many milliseconds ago a snowflake was generated, which is negative if its
+/// timestamp is in the future.
+///
+/// # Panics
+/// Panics if the current time is behind the Unix Epoch.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{age_of, generate_snowflake};
+/// assert!(age_of(generate_snowflake::<0>(0, 0)) >= 0);
+/// ```
+#[inline]
+pub fn age_of(id: u128) -> i128 {
+    get_epoch_time() as i128 - timestamp_of(id) as i128
+}
+
+/// Describes how long ago a snowflake was generated in English, such as `"3 minutes ago"`, or
+/// `"in 2 hours"` if its timestamp is in the future.
+///
+/// The age from [`age_of`] is rounded down to the largest whole unit out of years (of 365
+/// days), days, hours, minutes and seconds. Ages under a second are described as `"just now"`.
+///
+/// # Panics
+/// Panics if the current time is behind the Unix Epoch.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{generate_snowflake, relative_time_of};
+/// assert_eq!(relative_time_of(generate_snowflake::<0>(0, 0)), "just now");
+/// ```
+pub fn relative_time_of(id: u128) -> String {
+    let age = age_of(id);
+    let magnitude = age.unsigned_abs();
+    let Some(&(unit_ms, unit)) = UNITS.iter().find(|&&(unit_ms, _)| magnitude >= unit_ms) else {
+        return "just now".to_owned();
+    };
+    let count = magnitude / unit_ms;
+    let plural = if count == 1 { "" } else { "s" };
+    if age < 0 {
+        format!("in {} {}{}", count, unit, plural)
+    } else {
+        format!("{} {}{} ago", count, unit, plural)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_snowflake, pack_with_counter, with_frozen_time};
+
+    const HOUR_MS: u128 = 60 * 60 * 1_000;
+
+    #[test]
+    fn fresh_snowflakes_are_just_now() {
+        let id = generate_snowflake::<0>(0, 0);
+        assert_eq!(relative_time_of(id), "just now");
+    }
+
+    #[test]
+    fn old_and_future_snowflakes() {
+        let id = pack_with_counter::<0>(10 * HOUR_MS, 0, 0, 0);
+        let at = |now: u128| with_frozen_time(now, || (age_of(id), relative_time_of(id)));
+
+        assert_eq!(at(10 * HOUR_MS + 999), (999, "just now".to_owned()));
+        assert_eq!(at(10 * HOUR_MS + 1_000).1, "1 second ago");
+        assert_eq!(at(10 * HOUR_MS + 3 * 60_000 + 59_999).1, "3 minutes ago");
+        assert_eq!(at(12 * HOUR_MS).1, "2 hours ago");
+        assert_eq!(at(10 * HOUR_MS + 24 * HOUR_MS).1, "1 day ago");
+        assert_eq!(at(10 * HOUR_MS + 2 * 365 * 24 * HOUR_MS).1, "2 years ago");
+
+        assert_eq!(
+            at(8 * HOUR_MS),
+            (-2 * HOUR_MS as i128, "in 2 hours".to_owned())
+        );
+        assert_eq!(at(10 * HOUR_MS - 1).1, "just now");
+        assert_eq!(at(10 * HOUR_MS - 60_000).1, "in 1 minute");
+    }
+}
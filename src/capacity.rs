@@ -0,0 +1,100 @@
+use crate::Counter;
+
+/// Estimates the probability that at least two of `ids_per_ms` snowflakes generated on the same
+/// node in the same millisecond with counters of type `C` collide.
+///
+/// This uses the birthday-paradox model: every snowflake's counter is assumed to be drawn
+/// independently and uniformly from the `2^C::BITS` values the counter can take, as is
+/// effectively the case when several uncoordinated processes share a node ID. The probability is
+/// approximated as `1 - e^(-n(n - 1) / 2N)` for `n` snowflakes and `N` counter values.
+///
+/// A single generator counts sequentially, so it can't collide until its counter wraps: treat
+/// this as a pessimistic estimate for deciding how aggressively to shard node IDs.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::collision_probability;
+/// use std::sync::atomic::{AtomicU16, AtomicU32};
+/// assert!(collision_probability::<AtomicU16>(1_000) > collision_probability::<AtomicU16>(100));
+/// assert!(collision_probability::<AtomicU32>(1_000) < collision_probability::<AtomicU16>(1_000));
+/// ```
+#[must_use]
+pub fn collision_probability<C: Counter>(ids_per_ms: u32) -> f64 {
+    let n = f64::from(ids_per_ms);
+    if n < 2.0 {
+        return 0.0;
+    }
+    let counter_values = (1u64 << C::BITS) as f64;
+    -(-n * (n - 1.0) / (2.0 * counter_values)).exp_m1()
+}
+
+/// Returns how many whole milliseconds into a sustained burst of `rate_per_ms` snowflakes per
+/// millisecond on one node a generator counting with `C` runs out of counter values, or `None` if
+/// it never does.
+///
+/// The counter itself is a wrapping atomic that never resets, so every snowflake stamped with the
+/// same millisecond is distinct as long as fewer than `2^C::BITS` of them are generated in it. What
+/// starts over every millisecond is only the generator's count of how many it has stamped so far,
+/// which its [`CounterPolicy`](crate::CounterPolicy) checks against the counter's width. This
+/// makes exhaustion all or nothing: a rate up to `2^C::BITS` is sustainable forever, and any
+/// higher rate runs out partway through the very first millisecond, so the answer is always
+/// either `None` or `Some(0)`.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::ms_until_exhaustion;
+/// use std::sync::atomic::{AtomicU16, AtomicU32, AtomicU8};
+/// assert_eq!(ms_until_exhaustion::<AtomicU16>(50_000), None);
+/// assert_eq!(ms_until_exhaustion::<AtomicU16>(100_000), Some(0));
+/// assert_eq!(ms_until_exhaustion::<AtomicU8>(1_000), Some(0));
+/// assert_eq!(ms_until_exhaustion::<AtomicU32>(u32::MAX), None);
+/// ```
+#[must_use]
+pub fn ms_until_exhaustion<C: Counter>(rate_per_ms: u32) -> Option<u128> {
+    (u64::from(rate_per_ms) > 1 << C::BITS).then_some(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU16, AtomicU32, AtomicU8};
+
+    #[test]
+    fn probability_grows_with_the_rate() {
+        let probability = collision_probability::<AtomicU16>;
+        assert_eq!(probability(0), 0.0);
+        assert_eq!(probability(1), 0.0);
+        assert!(probability(2) < 1e-4);
+        assert!(probability(10) < 1e-3);
+        // around the square root of the counter width, a collision is a coin flip
+        assert!((probability(301) - 0.5).abs() < 0.01);
+        assert!(probability(2_000) > 0.999_999);
+        assert!(probability(65_537) > 1.0 - f64::EPSILON);
+        assert!(probability(u32::MAX) <= 1.0);
+    }
+
+    #[test]
+    fn probability_follows_the_counter_width() {
+        // the coin flip moves with the square root of the counter width
+        assert!((collision_probability::<AtomicU8>(19) - 0.5).abs() < 0.02);
+        assert!((collision_probability::<AtomicU32>(77_163) - 0.5).abs() < 0.01);
+        assert_eq!(collision_probability::<AtomicU32>(1), 0.0);
+    }
+
+    #[test]
+    fn exhaustion_only_above_capacity() {
+        assert_eq!(ms_until_exhaustion::<AtomicU16>(0), None);
+        assert_eq!(ms_until_exhaustion::<AtomicU16>(1_000), None);
+        assert_eq!(ms_until_exhaustion::<AtomicU16>(65_536), None);
+        assert_eq!(ms_until_exhaustion::<AtomicU16>(65_537), Some(0));
+        assert_eq!(ms_until_exhaustion::<AtomicU16>(u32::MAX), Some(0));
+    }
+
+    #[test]
+    fn exhaustion_follows_the_counter_width() {
+        assert_eq!(ms_until_exhaustion::<AtomicU8>(256), None);
+        assert_eq!(ms_until_exhaustion::<AtomicU8>(257), Some(0));
+        // a 32-bit counter can tell apart more snowflakes than a u32 rate can ask for
+        assert_eq!(ms_until_exhaustion::<AtomicU32>(u32::MAX), None);
+    }
+}
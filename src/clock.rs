@@ -0,0 +1,247 @@
+#[cfg(any(test, feature = "frozen-time"))]
+use crate::FERRIS_EPOCH;
+#[cfg(any(test, feature = "frozen-time"))]
+use std::cell::Cell;
+#[cfg(not(feature = "wasm"))]
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+#[cfg(not(feature = "wasm"))]
+use std::sync::Arc;
+#[cfg(feature = "wasm")]
+use std::sync::{PoisonError, RwLock};
+#[cfg(not(feature = "wasm"))]
+use std::thread::JoinHandle;
+use std::time::Duration;
+#[cfg(not(feature = "wasm"))]
+use std::time::Instant;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(any(test, feature = "frozen-time"))]
+thread_local! {
+    /// The Ferris Epoch time the system clock is frozen at on this thread, if any.
+    static FROZEN_TIME: Cell<Option<u128>> = const { Cell::new(None) };
+}
+
+/// Where the system clock reads the time from instead of `SystemTime`, if set.
+#[cfg(feature = "wasm")]
+static TIME_SOURCE: RwLock<Option<fn() -> u128>> = RwLock::new(None);
+
+/// A source of wall-clock time for a [`SnowflakeGenerator`](crate::SnowflakeGenerator).
+///
+/// This is implemented for any `Fn() -> u128`, which makes it easy to drive a generator from a
+/// mocked clock in tests.
+pub trait Clock: Send + Sync {
+    /// Returns the current time in milliseconds since the Unix Epoch.
+    fn now_ms(&self) -> u128;
+}
+
+impl<F: Fn() -> u128 + Send + Sync> Clock for F {
+    #[inline]
+    fn now_ms(&self) -> u128 {
+        self()
+    }
+}
+
+/// The system's wall clock, and the default [`Clock`] for every generator.
+///
+/// With the `wasm` feature, this reads the time source set with `set_time_source` instead.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    /// # Panics
+    /// Panics if the current time is behind the Unix Epoch.
+    #[inline]
+    fn now_ms(&self) -> u128 {
+        #[cfg(any(test, feature = "frozen-time"))]
+        if let Some(frozen) = frozen_time() {
+            return frozen + FERRIS_EPOCH;
+        }
+        #[cfg(feature = "wasm")]
+        if let Some(source) = *TIME_SOURCE.read().unwrap_or_else(PoisonError::into_inner) {
+            return source();
+        }
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("you're behind the Unix Epoch")
+            .as_millis()
+    }
+}
+
+/// Makes the system clock read the time from `source`, in milliseconds since the Unix Epoch,
+/// instead of `SystemTime`, for every thread and every generator using the [`SystemClock`].
+///
+/// `SystemTime` panics on `wasm32-unknown-unknown`, so the host must provide the time itself,
+/// and this must be called before generating any snowflakes. With
+/// [`js-sys`](https://docs.rs/js-sys), that looks like:
+/// ```rust,ignore
+/// ferrischat_snowflake_generator::set_time_source(|| js_sys::Date::now() as u128);
+/// ```
+///
+/// Setting the time source again replaces the previous one. This is only available with the
+/// `wasm` feature.
+#[cfg(feature = "wasm")]
+pub fn set_time_source(source: fn() -> u128) {
+    *TIME_SOURCE.write().unwrap_or_else(PoisonError::into_inner) = Some(source);
+}
+
+/// Blocks for `ms` milliseconds.
+///
+/// With the `wasm` feature, where threads can't sleep, this spins until `now` (in milliseconds)
+/// has advanced by `ms` instead.
+#[cfg(feature = "time-safety-checks")]
+#[cold]
+pub(crate) fn pause(ms: u128, now: impl Fn() -> u128) {
+    #[cfg(not(feature = "wasm"))]
+    {
+        let _ = now;
+        std::thread::sleep(Duration::from_millis(ms as u64));
+    }
+    #[cfg(feature = "wasm")]
+    {
+        let until = now() + ms;
+        while now() < until {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+/// A [`Clock`] that reads another clock on a background thread every refresh interval, and
+/// returns the last time it read instead of reading the clock itself.
+#[cfg(not(feature = "wasm"))]
+pub(crate) struct CachedClock {
+    /// The last time read, in milliseconds since the Unix Epoch.
+    now: Arc<AtomicU64>,
+    /// Set when the clock is dropped, to stop the background thread.
+    stop: Arc<AtomicBool>,
+    refresher: Option<JoinHandle<()>>,
+}
+
+#[cfg(not(feature = "wasm"))]
+impl CachedClock {
+    pub(crate) fn new(clock: Box<dyn Clock>, refresh_interval: Duration) -> Self {
+        let now = Arc::new(AtomicU64::new(clock.now_ms() as u64));
+        let stop = Arc::new(AtomicBool::new(false));
+        let refresher = {
+            let now = Arc::clone(&now);
+            let stop = Arc::clone(&stop);
+            std::thread::Builder::new()
+                .name("snowflake-clock".into())
+                .spawn(move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        now.store(clock.now_ms() as u64, Ordering::Relaxed);
+                        std::thread::park_timeout(refresh_interval);
+                    }
+                })
+                .expect("failed to spawn the clock refresh thread")
+        };
+        Self {
+            now,
+            stop,
+            refresher: Some(refresher),
+        }
+    }
+}
+
+#[cfg(not(feature = "wasm"))]
+impl Clock for CachedClock {
+    #[inline]
+    fn now_ms(&self) -> u128 {
+        self.now.load(Ordering::Relaxed) as u128
+    }
+}
+
+#[cfg(not(feature = "wasm"))]
+impl Drop for CachedClock {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(refresher) = self.refresher.take() {
+            refresher.thread().unpark();
+            let _ = refresher.join();
+        }
+    }
+}
+
+/// Measures how much time has passed since it was started.
+///
+/// With the `wasm` feature, where `Instant` isn't available, this reads the [`SystemClock`]
+/// instead.
+pub(crate) struct Stopwatch {
+    #[cfg(not(feature = "wasm"))]
+    start: Instant,
+    #[cfg(feature = "wasm")]
+    start_ms: u128,
+}
+
+impl Stopwatch {
+    #[inline]
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(not(feature = "wasm"))]
+            start: Instant::now(),
+            #[cfg(feature = "wasm")]
+            start_ms: SystemClock.now_ms(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn elapsed(&self) -> Duration {
+        #[cfg(not(feature = "wasm"))]
+        return self.start.elapsed();
+        #[cfg(feature = "wasm")]
+        return Duration::from_millis(SystemClock.now_ms().saturating_sub(self.start_ms) as u64);
+    }
+}
+
+/// Returns the Ferris Epoch time the system clock is frozen at on this thread, if any.
+#[cfg(any(test, feature = "frozen-time"))]
+#[inline]
+pub(crate) fn frozen_time() -> Option<u128> {
+    FROZEN_TIME.with(Cell::get)
+}
+
+/// Runs `f` with the system clock frozen at `ms` milliseconds since the Ferris Epoch on the
+/// current thread, so [`get_epoch_time`](crate::get_epoch_time) and every generator using the
+/// [`SystemClock`] see that time until `f` returns.
+///
+/// Clock rollback checks are skipped for the free functions while the clock is frozen, so
+/// freezing it in the past doesn't block.
+///
+/// This is only meant for testing time-dependent code, and is only available with the
+/// `frozen-time` feature: never enable it in production builds.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{get_epoch_time, with_frozen_time};
+/// assert_eq!(with_frozen_time(42, get_epoch_time), 42);
+/// ```
+#[cfg(any(test, feature = "frozen-time"))]
+pub fn with_frozen_time<T>(ms: u128, f: impl FnOnce() -> T) -> T {
+    /// Restores the previous frozen time, even if `f` panics.
+    struct Restore(Option<u128>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            FROZEN_TIME.with(|frozen| frozen.set(self.0));
+        }
+    }
+
+    let _restore = Restore(FROZEN_TIME.with(|frozen| frozen.replace(Some(ms))));
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_snowflake, get_epoch_time, timestamp_of};
+
+    #[test]
+    fn frozen_time_is_shared_by_generations() {
+        let (snowflake_1, snowflake_2) = with_frozen_time(1_234, || {
+            (generate_snowflake::<0>(0, 0), generate_snowflake::<0>(0, 0))
+        });
+        assert_eq!(timestamp_of(snowflake_1), 1_234);
+        assert_eq!(timestamp_of(snowflake_2), 1_234);
+        assert_ne!(snowflake_1, snowflake_2);
+        assert!(get_epoch_time() > 1_234);
+    }
+}
@@ -0,0 +1,49 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// How often a generator resyncs with its cluster clock, in milliseconds of local time.
+const SYNC_INTERVAL_MS: u64 = 1_000;
+
+/// A logical cluster clock that a generator periodically syncs its timestamps to.
+pub(crate) struct ClusterClock {
+    /// Returns the authoritative cluster time, in milliseconds since the Unix Epoch.
+    now: Box<dyn Fn() -> u128 + Send + Sync>,
+    /// How far the cluster clock was ahead of the local clock at the last sync, in milliseconds.
+    offset: AtomicU64,
+    /// The local epoch time at which the next sync is due.
+    next_sync: AtomicU64,
+}
+
+impl ClusterClock {
+    pub(crate) fn new(now: impl Fn() -> u128 + Send + Sync + 'static) -> Self {
+        Self {
+            now: Box::new(now),
+            offset: AtomicU64::new(0),
+            next_sync: AtomicU64::new(0),
+        }
+    }
+
+    /// Adjusts a local time since `epoch` (in milliseconds since the Unix Epoch) to
+    /// `max(local, cluster)`, resyncing if one is due.
+    pub(crate) fn adjust(&self, local: u128, epoch: u128) -> u128 {
+        let local_ms = local as u64;
+        let next_sync = self.next_sync.load(Ordering::Relaxed);
+        // only one caller gets to resync per interval, everyone else uses the current offset
+        if local_ms >= next_sync
+            && self
+                .next_sync
+                .compare_exchange(
+                    next_sync,
+                    local_ms + SYNC_INTERVAL_MS,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                )
+                .is_ok()
+        {
+            let cluster = (self.now)().saturating_sub(epoch);
+            let offset = cluster.saturating_sub(local) as u64;
+            // the offset never shrinks, so a generator never goes backwards in time
+            self.offset.fetch_max(offset, Ordering::Relaxed);
+        }
+        local + self.offset.load(Ordering::Relaxed) as u128
+    }
+}
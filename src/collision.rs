@@ -0,0 +1,46 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Mutex, PoisonError};
+
+/// Remembers the most recently generated snowflakes so duplicates can be caught.
+pub(crate) struct CollisionDetector {
+    /// How many snowflakes to remember.
+    capacity: usize,
+    /// The remembered snowflakes, both in generation order and for lookups.
+    recent: Mutex<(VecDeque<u128>, HashSet<u128>)>,
+}
+
+impl CollisionDetector {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            recent: Mutex::new((
+                VecDeque::with_capacity(capacity),
+                HashSet::with_capacity(capacity),
+            )),
+        }
+    }
+
+    /// Remembers `id`, forgetting the oldest snowflake if full.
+    ///
+    /// # Panics
+    /// Panics if `id` is already remembered.
+    pub(crate) fn check(&self, id: u128) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut recent = self.recent.lock().unwrap_or_else(PoisonError::into_inner);
+        let (order, seen) = &mut *recent;
+        assert!(
+            seen.insert(id),
+            "generated snowflake {} was already issued within the last {} snowflakes",
+            id,
+            self.capacity
+        );
+        if order.len() == self.capacity {
+            if let Some(oldest) = order.pop_front() {
+                seen.remove(&oldest);
+            }
+        }
+        order.push_back(id);
+    }
+}
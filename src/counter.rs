@@ -0,0 +1,160 @@
+use crate::{pack_fields, MODEL_TYPE_SHIFT};
+use std::sync::atomic::{AtomicU16, AtomicU32, AtomicU8, Ordering};
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// An atomic counter a [`SnowflakeGenerator`](crate::SnowflakeGenerator) can use to tell apart
+/// snowflakes generated in the same millisecond.
+///
+/// Wider counters allow more snowflakes per millisecond, at the cost of the bits below the model
+/// type shifting down to make room:
+/// * [`AtomicU8`]: 256 snowflakes per millisecond. The counter takes bits 72 to 79, the API
+///   version 80 to 87, the node 88 to 103, leaving bits 104 to 127 unused.
+/// * [`AtomicU16`]: 65,536 snowflakes per millisecond, laid out as documented at the crate root.
+///   This is the default.
+/// * [`AtomicU32`]: 4,294,967,296 snowflakes per millisecond. The counter takes bits 72 to 103,
+///   the API version 104 to 111, the node 112 to 127, leaving no bits unused.
+///
+/// The free decoding functions such as [`counter_of`](crate::counter_of) assume the default
+/// layout, so they only work on snowflakes from generators using [`AtomicU16`]. Decode the other
+/// widths with [`Counter::counter_of`], [`Counter::api_version_of`] and [`Counter::node_id_of`]
+/// instead, such as `AtomicU32::node_id_of(id)`. Their layouts have no system flag, shard, format
+/// version or reserved bits: with [`AtomicU32`] the node takes those bits over, so
+/// [`is_system`](crate::is_system), [`shard_of`](crate::shard_of),
+/// [`format_version_of`](crate::format_version_of) and
+/// [`looks_like_snowflake`](crate::looks_like_snowflake) return nonsense for its snowflakes.
+///
+/// This trait is sealed: it can't be implemented outside of this crate.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{Counter, SnowflakeGenerator};
+/// use std::sync::atomic::AtomicU32;
+/// let id = SnowflakeGenerator::with_counter(42, AtomicU32::new(0)).generate::<7>(0);
+/// assert_eq!(AtomicU32::node_id_of(id), 42);
+/// assert_eq!(AtomicU32::api_version_of(id), 7);
+/// ```
+pub trait Counter: private::Sealed + Default + Send + Sync {
+    /// How many bits wide the counter is.
+    const BITS: u32;
+
+    /// Increments the counter, wrapping on overflow, and returns its previous value.
+    fn fetch_increment(&self) -> u32;
+
+    /// Creates a counter starting at `seed`, truncated to the counter's width.
+    fn from_seed(seed: u32) -> Self;
+
+    /// Returns the counter of a snowflake packed with a counter of this width.
+    #[inline]
+    fn counter_of(id: u128) -> u32 {
+        let counter = id >> (MODEL_TYPE_SHIFT - Self::BITS);
+        (counter & ((1 << Self::BITS) - 1)) as u32
+    }
+
+    /// Returns the API version of a snowflake packed with a counter of this width.
+    #[inline]
+    fn api_version_of(id: u128) -> u8 {
+        (id >> (MODEL_TYPE_SHIFT - Self::BITS - 8)) as u8
+    }
+
+    /// Returns the node of a snowflake packed with a counter of this width.
+    #[inline]
+    fn node_id_of(id: u128) -> u16 {
+        (id >> (MODEL_TYPE_SHIFT - Self::BITS - 24)) as u16
+    }
+
+    /// Packs a snowflake with a counter of this width.
+    #[inline]
+    fn pack(
+        timestamp_ms: u128,
+        model_type: u8,
+        counter: u32,
+        api_version: u8,
+        node_id: u16,
+    ) -> u128 {
+        let counter_shift = MODEL_TYPE_SHIFT - Self::BITS;
+        let api_version_shift = counter_shift - 8;
+        let node_id_shift = api_version_shift - 16;
+        pack_fields(timestamp_ms, model_type, 0, 0, 0)
+            | ((counter as u128) << counter_shift)
+            | ((api_version as u128) << api_version_shift)
+            | ((node_id as u128) << node_id_shift)
+    }
+}
+
+macro_rules! impl_counter {
+    ($($atomic:ty => $bits:literal),* $(,)?) => {
+        $(
+            impl private::Sealed for $atomic {}
+
+            impl Counter for $atomic {
+                const BITS: u32 = $bits;
+
+                #[inline]
+                fn fetch_increment(&self) -> u32 {
+                    // fetch_add wraps on overflow: this is what we want
+                    self.fetch_add(1, Ordering::Relaxed) as u32
+                }
+
+                #[inline]
+                fn from_seed(seed: u32) -> Self {
+                    Self::new(seed as _)
+                }
+            }
+        )*
+    };
+}
+
+impl_counter!(AtomicU8 => 8, AtomicU16 => 16, AtomicU32 => 32);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{api_version_of, counter_of, node_id_of, pack_with_counter};
+
+    #[test]
+    fn default_width_matches_layout() {
+        assert_eq!(
+            AtomicU16::pack(1_234, 5, 6_789, 10, 11_121),
+            pack_with_counter::<10>(1_234, 5, 6_789, 11_121)
+        );
+    }
+
+    #[test]
+    fn default_width_decodes_like_the_free_functions() {
+        let id = pack_with_counter::<10>(1_234, 5, 6_789, 11_121);
+        assert_eq!(AtomicU16::counter_of(id), counter_of(id) as u32);
+        assert_eq!(AtomicU16::api_version_of(id), api_version_of(id));
+        assert_eq!(AtomicU16::node_id_of(id), node_id_of(id));
+    }
+
+    #[test]
+    fn fields_do_not_overlap() {
+        fn check<C: Counter>(max_counter: u32) {
+            let all = C::pack(u64::MAX as u128, u8::MAX, max_counter, u8::MAX, u16::MAX);
+            let fields = [
+                C::pack(u64::MAX as u128, 0, 0, 0, 0),
+                C::pack(0, u8::MAX, 0, 0, 0),
+                C::pack(0, 0, max_counter, 0, 0),
+                C::pack(0, 0, 0, u8::MAX, 0),
+                C::pack(0, 0, 0, 0, u16::MAX),
+            ];
+            assert_eq!(fields.iter().fold(0, |acc, field| acc | field), all);
+            assert_eq!(fields.iter().sum::<u128>(), all);
+            assert_eq!(C::counter_of(all), max_counter);
+            assert_eq!(C::api_version_of(all), u8::MAX);
+            assert_eq!(C::node_id_of(all), u16::MAX);
+
+            let id = C::pack(1_234, 5, 6, 7, 8);
+            assert_eq!(
+                (C::counter_of(id), C::api_version_of(id), C::node_id_of(id)),
+                (6, 7, 8)
+            );
+        }
+        check::<AtomicU8>(u8::MAX as u32);
+        check::<AtomicU16>(u16::MAX as u32);
+        check::<AtomicU32>(u32::MAX);
+    }
+}
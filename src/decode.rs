@@ -0,0 +1,555 @@
+use std::cmp::Ordering;
+
+use crate::{
+    get_epoch_time, MonotonicityViolation, SnowflakeError, API_VERSION_SHIFT, COUNTER_SHIFT,
+    FERRIS_EPOCH, FORMAT_VERSION_SHIFT, MAX_FORMAT_VERSION, MAX_SHARD, MODEL_TYPE_SHIFT,
+    NODE_ID_SHIFT, RESERVED_MASK, SHARD_SHIFT, SYSTEM_FLAG, TIMESTAMP_SHIFT,
+};
+
+/// Returns the timestamp of a snowflake, in milliseconds since the Ferris Epoch.
+#[inline]
+pub const fn timestamp_of(id: u128) -> u128 {
+    id >> TIMESTAMP_SHIFT
+}
+
+/// Returns the time a snowflake was generated, in milliseconds since the Unix Epoch.
+#[inline]
+pub const fn unix_timestamp_ms_of(id: u128) -> u128 {
+    timestamp_of(id) + FERRIS_EPOCH
+}
+
+/// Returns the time a snowflake was generated, in milliseconds since the Unix Epoch, rounded down
+/// to a multiple of `granularity_ms`.
+///
+/// This is handy for routing snowflakes to time-partitioned storage, such as hourly or daily
+/// tables. A `granularity_ms` of 0 leaves the time unrounded.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{pack_with_counter, time_bucket, FERRIS_EPOCH};
+/// let id = pack_with_counter::<0>(1_234, 0, 0, 0);
+/// assert_eq!(time_bucket(id, 1_000), FERRIS_EPOCH + 1_000);
+/// ```
+#[inline]
+pub const fn time_bucket(id: u128, granularity_ms: u128) -> u128 {
+    let unix_ms = unix_timestamp_ms_of(id);
+    match granularity_ms {
+        0 => unix_ms,
+        granularity_ms => unix_ms - unix_ms % granularity_ms,
+    }
+}
+
+/// Returns the model type a snowflake was generated for.
+#[inline]
+pub const fn model_type_of(id: u128) -> u8 {
+    (id >> MODEL_TYPE_SHIFT) as u8
+}
+
+/// Returns the value of the internal counter when a snowflake was generated.
+#[inline]
+pub const fn counter_of(id: u128) -> u16 {
+    (id >> COUNTER_SHIFT) as u16
+}
+
+/// Returns the API version a snowflake was generated with.
+#[inline]
+pub const fn api_version_of(id: u128) -> u8 {
+    (id >> API_VERSION_SHIFT) as u8
+}
+
+/// Returns whether a snowflake was generated with one of the `supported` API versions.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{generate_snowflake, supports_api_version};
+/// let id = generate_snowflake::<2>(0, 0);
+/// assert!(supports_api_version(id, &[2, 3]));
+/// assert!(!supports_api_version(id, &[3]));
+/// ```
+#[inline]
+pub fn supports_api_version(id: u128, supported: &[u8]) -> bool {
+    supported.contains(&api_version_of(id))
+}
+
+/// Returns the node a snowflake was generated on.
+#[inline]
+pub const fn node_id_of(id: u128) -> u16 {
+    (id >> NODE_ID_SHIFT) as u16
+}
+
+/// Returns a key shared by every snowflake created in the same millisecond, regardless of model
+/// type, counter, API version or node, for grouping snowflakes into timeline buckets.
+#[inline]
+pub const fn group_key(id: u128) -> u128 {
+    timestamp_of(id)
+}
+
+/// Returns a snowflake with its model type and API version zeroed, leaving the fields that
+/// identify it: timestamp, counter and node (plus the system flag and reserved bits).
+///
+/// This is handy as a cache key when those fields are irrelevant or can be re-derived. The result
+/// is not itself a valid snowflake and should never be handed out as one.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{identity_bits, pack_with_counter};
+/// let user = pack_with_counter::<1>(1_234, 0, 7, 1);
+/// let guild = pack_with_counter::<2>(1_234, 3, 7, 1);
+/// assert_eq!(identity_bits(user), identity_bits(guild));
+/// ```
+#[inline]
+pub const fn identity_bits(id: u128) -> u128 {
+    id & !((u8::MAX as u128) << MODEL_TYPE_SHIFT) & !((u8::MAX as u128) << API_VERSION_SHIFT)
+}
+
+/// Returns whether two snowflakes were created in the same millisecond.
+#[inline]
+pub const fn same_instant(a: u128, b: u128) -> bool {
+    group_key(a) == group_key(b)
+}
+
+/// Returns how many milliseconds after `b` the snowflake `a` was created, which is negative if
+/// `a` was created first and 0 if both were created in the same millisecond.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{pack_with_counter, time_diff_ms};
+/// let earlier = pack_with_counter::<0>(1_000, 0, 0, 0);
+/// let later = pack_with_counter::<0>(1_250, 0, 0, 0);
+/// assert_eq!(time_diff_ms(later, earlier), 250);
+/// assert_eq!(time_diff_ms(earlier, later), -250);
+/// ```
+#[inline]
+pub const fn time_diff_ms(a: u128, b: u128) -> i128 {
+    // timestamps are 64 bits wide, so this can't overflow
+    timestamp_of(a) as i128 - timestamp_of(b) as i128
+}
+
+/// Returns how far `b`'s counter is ahead of `a`'s when both were created in the same
+/// millisecond, or `None` otherwise.
+///
+/// The counter wraps, so the distance is taken the short way around: a counter of 0 is 1 ahead
+/// of a counter of 65,535. Snowflakes issued contiguously by one generator are exactly 1 apart.
+#[inline]
+pub const fn counter_distance(a: u128, b: u128) -> Option<i32> {
+    if !same_instant(a, b) {
+        return None;
+    }
+    Some(counter_of(b).wrapping_sub(counter_of(a)) as i16 as i32)
+}
+
+/// Compares two snowflakes by when they were generated: by timestamp, then by counter.
+///
+/// Snowflakes from the same millisecond are ordered by their counters regardless of model type,
+/// unlike comparing them as integers. Ties (which only happen across nodes or after the counter
+/// wraps) are broken by comparing the rest of the snowflake.
+#[inline]
+pub fn chronological_cmp(a: u128, b: u128) -> Ordering {
+    timestamp_of(a)
+        .cmp(&timestamp_of(b))
+        .then_with(|| counter_of(a).cmp(&counter_of(b)))
+        .then_with(|| a.cmp(&b))
+}
+
+/// Checks that every snowflake in `ids` is chronologically later than the one before it, as
+/// ordered by [`chronological_cmp`].
+///
+/// This is meant for auditing a persisted log of snowflakes from one generator, to confirm it
+/// never went backwards. Duplicates count as going backwards.
+///
+/// # Errors
+/// Returns the first snowflake that isn't later than its predecessor, along with its index.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{generate_snowflake, verify_monotonic};
+/// let ids = [generate_snowflake::<0>(0, 0), generate_snowflake::<0>(0, 0)];
+/// assert!(verify_monotonic(&ids).is_ok());
+/// ```
+pub fn verify_monotonic(ids: &[u128]) -> Result<(), MonotonicityViolation> {
+    match ids
+        .windows(2)
+        .position(|pair| chronological_cmp(pair[0], pair[1]) != Ordering::Less)
+    {
+        Some(i) => Err(MonotonicityViolation {
+            index: i + 1,
+            previous: ids[i],
+            current: ids[i + 1],
+        }),
+        None => Ok(()),
+    }
+}
+
+/// The highest API version [`looks_like_snowflake`] accepts.
+const PLAUSIBLE_MAX_API_VERSION: u8 = 31;
+/// How far in the future [`looks_like_snowflake`] accepts timestamps, in milliseconds, to allow for
+/// clock skew between the host that generated a snowflake and the one checking it.
+const PLAUSIBLE_CLOCK_SKEW_MS: u128 = 60_000;
+
+/// Returns whether `id` plausibly is a snowflake generated by this crate, for telling snowflakes
+/// apart from other identifiers in mixed data.
+///
+/// This is only a heuristic: it checks that the reserved bits are unset, that the timestamp is
+/// no more than a minute ahead of the current time, and that the API version is at most 31.
+/// Arbitrary values pass by chance, and snowflakes from generators with a custom epoch or a
+/// wider counter may not pass, so never rely on it for anything but filtering.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{generate_snowflake, looks_like_snowflake};
+/// assert!(looks_like_snowflake(generate_snowflake::<0>(0, 0)));
+/// assert!(!looks_like_snowflake(u128::MAX));
+/// ```
+pub fn looks_like_snowflake(id: u128) -> bool {
+    // the timestamp is relative to the Ferris Epoch, so it can't be before it
+    reserved_of(id) == 0
+        && timestamp_of(id) <= get_epoch_time() + PLAUSIBLE_CLOCK_SKEW_MS
+        && api_version_of(id) <= PLAUSIBLE_MAX_API_VERSION
+}
+
+/// Returns whether a snowflake is flagged as belonging to a system-generated entity.
+#[inline]
+pub const fn is_system(id: u128) -> bool {
+    id & SYSTEM_FLAG != 0
+}
+
+/// Returns the shard a snowflake routes to, which is 0 unless it was generated with
+/// [`generate_sharded`](crate::generate_sharded).
+#[inline]
+pub const fn shard_of(id: u128) -> u8 {
+    (id >> SHARD_SHIFT) as u8 & MAX_SHARD
+}
+
+/// Returns the version of the format a snowflake is laid out in, so that decoders can tell
+/// snowflakes from future versions of the format apart.
+///
+/// Every snowflake this crate generates is laid out in format version 0, as documented at the
+/// crate root.
+#[inline]
+pub const fn format_version_of(id: u128) -> u8 {
+    (id >> FORMAT_VERSION_SHIFT) as u8 & MAX_FORMAT_VERSION
+}
+
+/// Returns the value stored in the reserved bits of a snowflake.
+///
+/// Snowflakes generated by this crate always have every reserved bit unset.
+#[inline]
+pub const fn reserved_of(id: u128) -> u16 {
+    (id & RESERVED_MASK) as u16
+}
+
+/// Writes `value` into the reserved bits of a snowflake, leaving every other field untouched.
+///
+/// The crate doesn't give these bits a meaning yet, so applications are free to experiment with
+/// them, but a future version may standardize their use.
+///
+/// # Errors
+/// Returns [`SnowflakeError::ReservedOverflow`] if `value` doesn't fit in the reserved bits.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{generate_snowflake, reserved_of, set_reserved};
+/// let id = set_reserved(generate_snowflake::<0>(0, 0), 42).unwrap();
+/// assert_eq!(reserved_of(id), 42);
+/// ```
+#[inline]
+pub const fn set_reserved(id: u128, value: u16) -> Result<u128, SnowflakeError> {
+    if value as u128 & !RESERVED_MASK != 0 {
+        return Err(SnowflakeError::ReservedOverflow);
+    }
+    Ok((id & !RESERVED_MASK) | value as u128)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generate_sharded, generate_snowflake, generate_system_flagged, pack_with_counter,
+        with_frozen_time, without_counter, MAX_TIMESTAMP,
+    };
+
+    #[test]
+    fn identity_bits_ignore_model_type() {
+        let user = pack_with_counter::<0>(1_234, 1, 7, 42);
+        let guild = pack_with_counter::<0>(1_234, 2, 7, 42);
+        assert_ne!(user, guild);
+        assert_eq!(identity_bits(user), identity_bits(guild));
+        assert_eq!(model_type_of(identity_bits(user)), 0);
+        assert_eq!(timestamp_of(identity_bits(user)), 1_234);
+        assert_eq!(counter_of(identity_bits(user)), 7);
+        assert_eq!(node_id_of(identity_bits(user)), 42);
+        // a different counter is a different identity
+        let next = pack_with_counter::<0>(1_234, 1, 8, 42);
+        assert_ne!(identity_bits(user), identity_bits(next));
+    }
+
+    #[test]
+    fn packed_fields_decode_exactly() {
+        let id = pack_with_counter::<{ u8::MAX }>(1_234_567, 0xAB, u16::MAX, 0x1234);
+        assert_eq!(timestamp_of(id), 1_234_567);
+        assert_eq!(model_type_of(id), 0xAB);
+        assert_eq!(counter_of(id), u16::MAX);
+        assert_eq!(api_version_of(id), u8::MAX);
+        assert_eq!(node_id_of(id), 0x1234);
+    }
+
+    #[test]
+    fn time_buckets_split_at_boundaries() {
+        const HOUR_MS: u128 = 60 * 60 * 1_000;
+        const DAY_MS: u128 = 24 * HOUR_MS;
+
+        // the Ferris Epoch is midnight, so it starts both an hour and a day
+        let before_boundary = pack_with_counter::<0>(DAY_MS - 1, 0, 0, 0);
+        let after_boundary = pack_with_counter::<0>(DAY_MS, 0, 0, 0);
+        assert_eq!(
+            time_bucket(before_boundary, HOUR_MS),
+            FERRIS_EPOCH + DAY_MS - HOUR_MS
+        );
+        assert_eq!(time_bucket(after_boundary, HOUR_MS), FERRIS_EPOCH + DAY_MS);
+        assert_eq!(time_bucket(before_boundary, DAY_MS), FERRIS_EPOCH);
+        assert_eq!(time_bucket(after_boundary, DAY_MS), FERRIS_EPOCH + DAY_MS);
+
+        let later_that_day = pack_with_counter::<0>(DAY_MS + 5 * HOUR_MS + 1_234, 0, 0, 0);
+        assert_eq!(
+            time_bucket(later_that_day, HOUR_MS),
+            FERRIS_EPOCH + DAY_MS + 5 * HOUR_MS
+        );
+        assert_eq!(
+            time_bucket(later_that_day, DAY_MS),
+            time_bucket(after_boundary, DAY_MS)
+        );
+        assert_eq!(
+            time_bucket(later_that_day, 0),
+            unix_timestamp_ms_of(later_that_day)
+        );
+    }
+
+    #[test]
+    fn api_version_extraction() {
+        let id = generate_snowflake::<7>(0, 0);
+        assert_eq!(api_version_of(id), 7);
+        assert!(supports_api_version(id, &[5, 6, 7]));
+        assert!(!supports_api_version(id, &[5, 6, 8]));
+        assert!(!supports_api_version(id, &[]));
+    }
+
+    #[test]
+    fn same_millisecond_shares_group_key() {
+        let batch = [
+            pack_with_counter::<0>(1_000, 0, 0, 0),
+            pack_with_counter::<1>(1_000, 3, 1, 7),
+            pack_with_counter::<2>(1_000, u8::MAX, u16::MAX, u16::MAX),
+        ];
+        assert!(batch.iter().all(|&id| group_key(id) == group_key(batch[0])));
+        assert!(batch.iter().all(|&id| same_instant(id, batch[0])));
+
+        let later = pack_with_counter::<0>(1_001, 0, 0, 0);
+        assert_ne!(group_key(later), group_key(batch[0]));
+        assert!(!same_instant(later, batch[0]));
+    }
+
+    #[test]
+    fn time_diff_is_signed() {
+        let earlier = pack_with_counter::<0>(1_000, 0, u16::MAX, 0);
+        let later = pack_with_counter::<0>(1_042, 0, 0, 0);
+        assert_eq!(time_diff_ms(later, earlier), 42);
+        assert_eq!(time_diff_ms(earlier, later), -42);
+        let same = pack_with_counter::<3>(1_000, 2, 0, 1);
+        assert_eq!(time_diff_ms(earlier, same), 0);
+        assert_eq!(time_diff_ms(same, earlier), 0);
+
+        let last = pack_with_counter::<0>(MAX_TIMESTAMP, 0, 0, 0);
+        assert_eq!(time_diff_ms(last, 0), MAX_TIMESTAMP as i128);
+        assert_eq!(time_diff_ms(0, last), -(MAX_TIMESTAMP as i128));
+    }
+
+    #[test]
+    fn counter_distance_within_millisecond() {
+        let a = pack_with_counter::<0>(1_000, 0, 41, 0);
+        let b = pack_with_counter::<0>(1_000, 0, 42, 0);
+        assert_eq!(counter_distance(a, b), Some(1));
+        assert_eq!(counter_distance(b, a), Some(-1));
+        assert_eq!(counter_distance(a, a), Some(0));
+    }
+
+    #[test]
+    fn counter_distance_wraps_around() {
+        let a = pack_with_counter::<0>(1_000, 0, u16::MAX, 0);
+        let b = pack_with_counter::<0>(1_000, 0, 1, 0);
+        assert_eq!(counter_distance(a, b), Some(2));
+        assert_eq!(counter_distance(b, a), Some(-2));
+    }
+
+    #[test]
+    fn counter_distance_across_milliseconds() {
+        let a = pack_with_counter::<0>(1_000, 0, 41, 0);
+        let b = pack_with_counter::<0>(1_001, 0, 42, 0);
+        assert_eq!(counter_distance(a, b), None);
+    }
+
+    #[test]
+    fn chronological_order_ignores_model_type() {
+        let earlier = pack_with_counter::<0>(1_000, u8::MAX, 1, 0);
+        let later = pack_with_counter::<0>(1_000, 0, 2, 0);
+        assert!(earlier > later);
+        assert_eq!(chronological_cmp(earlier, later), Ordering::Less);
+        assert_eq!(chronological_cmp(later, earlier), Ordering::Greater);
+        assert_eq!(chronological_cmp(later, later), Ordering::Equal);
+
+        let next_millisecond = pack_with_counter::<0>(1_001, 0, 0, 0);
+        assert_eq!(chronological_cmp(later, next_millisecond), Ordering::Less);
+    }
+
+    #[test]
+    fn ascending_sequence_is_monotonic() {
+        let generator = crate::SnowflakeGenerator::new(0);
+        let ids: Vec<_> = (0..1_000).map(|_| generator.generate::<0>(0)).collect();
+        assert_eq!(verify_monotonic(&ids), Ok(()));
+        assert_eq!(verify_monotonic(&ids[..1]), Ok(()));
+        assert_eq!(verify_monotonic(&[]), Ok(()));
+    }
+
+    #[test]
+    fn regression_is_reported() {
+        let mut ids: Vec<_> = (0..10)
+            .map(|i| pack_with_counter::<0>(1_000 + i, 0, 0, 0))
+            .collect();
+        ids[6] = pack_with_counter::<0>(1_002, 0, 0, 0);
+        assert_eq!(
+            verify_monotonic(&ids),
+            Err(MonotonicityViolation {
+                index: 6,
+                previous: ids[5],
+                current: ids[6],
+            })
+        );
+
+        let duplicated = [ids[0], ids[1], ids[1]];
+        assert_eq!(verify_monotonic(&duplicated).unwrap_err().index, 2);
+    }
+
+    #[test]
+    fn reserved_round_trips() {
+        let id = pack_with_counter::<{ u8::MAX }>(1_234_567, 0xAB, u16::MAX, u16::MAX);
+        for value in [0, 1, 0x123, RESERVED_MASK as u16] {
+            let with_reserved = set_reserved(id, value).unwrap();
+            assert_eq!(reserved_of(with_reserved), value);
+            assert_eq!(with_reserved & !RESERVED_MASK, id);
+            assert!(!is_system(with_reserved));
+        }
+    }
+
+    #[test]
+    fn oversized_reserved_errors() {
+        let id = pack_with_counter::<0>(1_234_567, 0, 0, 0);
+        assert_eq!(
+            set_reserved(id, RESERVED_MASK as u16 + 1),
+            Err(SnowflakeError::ReservedOverflow)
+        );
+        assert_eq!(
+            set_reserved(id, u16::MAX),
+            Err(SnowflakeError::ReservedOverflow)
+        );
+    }
+
+    #[test]
+    fn shards_round_trip() {
+        for shard in 0..=MAX_SHARD {
+            let id = generate_sharded::<3>(4, 5, shard).unwrap();
+            assert_eq!(shard_of(id), shard);
+            assert_eq!(model_type_of(id), 4);
+            assert_eq!(api_version_of(id), 3);
+            assert_eq!(node_id_of(id), 5);
+            assert_eq!(reserved_of(id), 0);
+            assert!(!is_system(id));
+        }
+        assert_eq!(shard_of(generate_snowflake::<3>(4, 5)), 0);
+        assert_eq!(shard_of(generate_system_flagged::<3>(4, 5, true)), 0);
+        assert_eq!(shard_of(set_reserved(0, RESERVED_MASK as u16).unwrap()), 0);
+    }
+
+    #[test]
+    fn oversized_shard_errors() {
+        for shard in [MAX_SHARD + 1, u8::MAX] {
+            assert_eq!(
+                generate_sharded::<0>(0, 0, shard),
+                Err(SnowflakeError::ShardOverflow)
+            );
+        }
+    }
+
+    #[test]
+    fn generated_snowflakes_look_like_snowflakes() {
+        for id in [
+            generate_snowflake::<0>(0, 0),
+            generate_snowflake::<3>(u8::MAX, u16::MAX),
+            generate_system_flagged::<1>(2, 3, true),
+            generate_sharded::<1>(2, 3, MAX_SHARD).unwrap(),
+        ] {
+            assert!(looks_like_snowflake(id), "{:#x} doesn't look right", id);
+        }
+    }
+
+    #[test]
+    fn other_identifiers_do_not_look_like_snowflakes() {
+        let id = generate_snowflake::<0>(0, 0);
+        // dirty reserved bits
+        assert!(!looks_like_snowflake(
+            0x9E37_79B9_7F4A_7C15_F39C_C060_5CED_C834
+        ));
+        assert!(!looks_like_snowflake(id | 1));
+        // far in the future
+        let future = pack_with_counter::<0>(get_epoch_time() + 60 * 60 * 1_000, 0, 0, 0);
+        assert!(!looks_like_snowflake(future));
+        // an API version that doesn't exist
+        assert!(!looks_like_snowflake(pack_with_counter::<{ u8::MAX }>(
+            1, 0, 0, 0
+        )));
+    }
+
+    #[test]
+    fn generated_snowflakes_are_format_version_0() {
+        for id in [
+            generate_snowflake::<3>(u8::MAX, u16::MAX),
+            generate_system_flagged::<3>(4, 5, true),
+            generate_sharded::<3>(4, 5, MAX_SHARD).unwrap(),
+            set_reserved(0, RESERVED_MASK as u16).unwrap(),
+        ] {
+            assert_eq!(format_version_of(id), 0);
+        }
+        let id = generate_snowflake::<0>(0, 0);
+        for version in 0..=MAX_FORMAT_VERSION {
+            let tagged = id | (version as u128) << FORMAT_VERSION_SHIFT;
+            assert_eq!(format_version_of(tagged), version);
+            assert_eq!(reserved_of(tagged), 0);
+            assert_eq!(shard_of(tagged), 0);
+        }
+    }
+
+    #[test]
+    fn system_flag() {
+        let user = generate_system_flagged::<3>(4, 5, false);
+        let system = generate_system_flagged::<3>(4, 5, true);
+        assert!(!is_system(user));
+        assert!(is_system(system));
+        for id in [user, system] {
+            assert_eq!(model_type_of(id), 4);
+            assert_eq!(api_version_of(id), 3);
+            assert_eq!(node_id_of(id), 5);
+            assert_eq!(reserved_of(id), 0);
+        }
+    }
+
+    #[test]
+    fn unflagged_generation_is_unchanged() {
+        let (plain, unflagged) = with_frozen_time(1_234, || {
+            (
+                generate_snowflake::<3>(4, 5),
+                generate_system_flagged::<3>(4, 5, false),
+            )
+        });
+        assert!(!is_system(plain));
+        // the only difference should be the counter
+        assert_eq!(without_counter(plain), without_counter(unflagged));
+    }
+}
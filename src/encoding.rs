@@ -0,0 +1,358 @@
+use crate::DecodeError;
+
+/// The base62 alphabet, in ascending digit value.
+const BASE62_ALPHABET: &[u8; 62] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+/// The modulus used for check characters: the largest prime that fits in one base62 digit.
+const CHECKSUM_MODULUS: u32 = 61;
+/// Crockford's base32 alphabet, in ascending digit value (and ascending ASCII order).
+const BASE32_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+/// How many characters a sortable key is long: enough base32 digits for 128 bits.
+const SORTABLE_KEY_LEN: usize = 26;
+/// The bit set on every varint byte that is followed by another one.
+const VARINT_CONTINUATION: u8 = 0x80;
+/// How many bytes `u128::MAX` takes up as a varint.
+const MAX_VARINT_LEN: usize = 19;
+
+/// Returns the value of a base62 digit, or `None` if the character isn't in the alphabet.
+#[inline]
+fn base62_digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'A'..=b'Z' => Some(c - b'A' + 10),
+        b'a'..=b'z' => Some(c - b'a' + 36),
+        _ => None,
+    }
+}
+
+/// Computes the check digit for a sequence of base62 digit values.
+#[inline]
+fn checksum(digits: impl Iterator<Item = u8>) -> u8 {
+    let sum = digits
+        .zip(1..)
+        .fold(0, |acc, (d, pos)| (acc + d as u32 * pos) % CHECKSUM_MODULUS);
+    sum as u8
+}
+
+/// Encodes a snowflake as a base62 string (`0-9`, `A-Z`, `a-z`), most significant digit first.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{decode_base62, encode_base62};
+/// assert_eq!(encode_base62(61), "z");
+/// assert_eq!(decode_base62(&encode_base62(u128::MAX)), Ok(u128::MAX));
+/// ```
+pub fn encode_base62(mut id: u128) -> String {
+    // u128::MAX is 22 digits long in base62
+    let mut buf = [0; 22];
+    let mut start = buf.len();
+    loop {
+        start -= 1;
+        buf[start] = BASE62_ALPHABET[(id % 62) as usize];
+        id /= 62;
+        if id == 0 {
+            break;
+        }
+    }
+    buf[start..].iter().map(|&b| b as char).collect()
+}
+
+/// Decodes a base62 string produced by [`encode_base62`].
+///
+/// # Errors
+/// Returns an error if the input is empty, contains a character outside the base62 alphabet,
+/// or decodes to a value larger than `u128::MAX`.
+pub fn decode_base62(s: &str) -> Result<u128, DecodeError> {
+    if s.is_empty() {
+        return Err(DecodeError::Empty);
+    }
+    s.chars().try_fold(0_u128, |acc, c| {
+        let digit = u8::try_from(c)
+            .ok()
+            .and_then(base62_digit)
+            .ok_or(DecodeError::InvalidCharacter(c))?;
+        acc.checked_mul(62)
+            .and_then(|acc| acc.checked_add(digit as u128))
+            .ok_or(DecodeError::Overflow)
+    })
+}
+
+/// Encodes a snowflake as base62 with a trailing check character, to catch typos in IDs
+/// that humans copy around by hand.
+///
+/// The check character is computed over the base62 digits of the ID: each digit's value is
+/// multiplied by its 1-based position from the left, the products are summed modulo 61, and the
+/// result is appended as one more base62 digit. Because 61 is prime and longer than any encoded
+/// ID, this detects every single-character substitution and every swap of two adjacent
+/// characters, except for substitutions or swaps between `0` and `z` (whose values differ by
+/// exactly 61).
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{decode_base62_with_checksum, encode_base62_with_checksum};
+/// let encoded = encode_base62_with_checksum(1234567890);
+/// assert_eq!(decode_base62_with_checksum(&encoded), Ok(1234567890));
+/// ```
+pub fn encode_base62_with_checksum(id: u128) -> String {
+    let mut encoded = encode_base62(id);
+    let check = checksum(encoded.bytes().filter_map(base62_digit));
+    encoded.push(BASE62_ALPHABET[check as usize] as char);
+    encoded
+}
+
+/// Decodes a string produced by [`encode_base62_with_checksum`], validating its check character.
+///
+/// # Errors
+/// Returns [`DecodeError::ChecksumMismatch`] if the check character doesn't match the rest of the
+/// input, as well as any error [`decode_base62`] would return for the remaining digits.
+pub fn decode_base62_with_checksum(s: &str) -> Result<u128, DecodeError> {
+    let mut chars = s.chars();
+    let check = chars.next_back().ok_or(DecodeError::Empty)?;
+    let body = chars.as_str();
+    let id = decode_base62(body)?;
+    let expected = BASE62_ALPHABET[checksum(body.bytes().filter_map(base62_digit)) as usize];
+    if check != expected as char {
+        return Err(DecodeError::ChecksumMismatch);
+    }
+    Ok(id)
+}
+
+/// Returns the value of a Crockford base32 digit, case-insensitively, or `None` if the character
+/// isn't in the alphabet.
+#[inline]
+fn base32_digit(c: u8) -> Option<u8> {
+    let c = c.to_ascii_uppercase();
+    BASE32_ALPHABET
+        .iter()
+        .position(|&digit| digit == c)
+        .map(|value| value as u8)
+}
+
+/// Encodes a snowflake as a fixed-width, 26-character key that sorts lexicographically in the
+/// same order as the snowflakes themselves, for time-sortable object storage keys.
+///
+/// The key is the snowflake in Crockford's base32 (`0-9` and `A-Z` without `I`, `L`, `O` and
+/// `U`), most significant digit first and zero-padded. The alphabet only uses one case and is in
+/// ascending ASCII order, so keys sort correctly even where names are case-insensitive.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{from_sortable_key, to_sortable_key};
+/// assert_eq!(to_sortable_key(42), "0000000000000000000000001A");
+/// assert_eq!(from_sortable_key(&to_sortable_key(u128::MAX)), Ok(u128::MAX));
+/// ```
+pub fn to_sortable_key(mut id: u128) -> String {
+    let mut buf = [b'0'; SORTABLE_KEY_LEN];
+    for digit in buf.iter_mut().rev() {
+        *digit = BASE32_ALPHABET[(id % 32) as usize];
+        id /= 32;
+    }
+    buf.iter().map(|&b| b as char).collect()
+}
+
+/// Decodes a key produced by [`to_sortable_key`], case-insensitively.
+///
+/// # Errors
+/// Returns an error if the input is empty, isn't exactly 26 characters long, contains a
+/// character outside the base32 alphabet, or decodes to a value larger than `u128::MAX`.
+pub fn from_sortable_key(key: &str) -> Result<u128, DecodeError> {
+    if key.is_empty() {
+        return Err(DecodeError::Empty);
+    }
+    if let Some(c) = key
+        .chars()
+        .find(|&c| !c.is_ascii() || base32_digit(c as u8).is_none())
+    {
+        return Err(DecodeError::InvalidCharacter(c));
+    }
+    if key.len() != SORTABLE_KEY_LEN {
+        return Err(DecodeError::InvalidLength);
+    }
+    key.bytes().try_fold(0_u128, |acc, c| {
+        let digit = base32_digit(c).ok_or(DecodeError::InvalidCharacter(c as char))?;
+        acc.checked_mul(32)
+            .and_then(|acc| acc.checked_add(digit as u128))
+            .ok_or(DecodeError::Overflow)
+    })
+}
+
+/// Encodes a snowflake as an unsigned LEB128 varint: 7 bits per byte, least significant group
+/// first, with the high bit of every byte but the last set.
+///
+/// Leading zero bits are dropped, so snowflakes from early in the Ferris Epoch take up fewer
+/// bytes, up to 19 bytes for `u128::MAX`.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{from_varint, to_varint};
+/// assert_eq!(to_varint(300), [0xAC, 0x02]);
+/// assert_eq!(from_varint(&[0xAC, 0x02, 0xFF]), Ok((300, 2)));
+/// ```
+pub fn to_varint(mut id: u128) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(MAX_VARINT_LEN);
+    loop {
+        let byte = (id & 0x7F) as u8;
+        id >>= 7;
+        if id == 0 {
+            encoded.push(byte);
+            return encoded;
+        }
+        encoded.push(byte | VARINT_CONTINUATION);
+    }
+}
+
+/// Decodes a varint produced by [`to_varint`] from the start of `bytes`, returning the snowflake
+/// and how many bytes it took up.
+///
+/// Any bytes after the end of the varint are ignored, so a stream of varints can be decoded by
+/// repeatedly skipping past the bytes consumed.
+///
+/// # Errors
+/// Returns [`DecodeError::Empty`] if `bytes` is empty, [`DecodeError::Truncated`] if it ends
+/// before the varint does, or [`DecodeError::Overflow`] if the varint decodes to a value larger
+/// than `u128::MAX`.
+pub fn from_varint(bytes: &[u8]) -> Result<(u128, usize), DecodeError> {
+    if bytes.is_empty() {
+        return Err(DecodeError::Empty);
+    }
+    let mut id = 0_u128;
+    for (i, &byte) in bytes.iter().enumerate() {
+        let group = (byte & !VARINT_CONTINUATION) as u128;
+        let shift = i as u32 * 7;
+        if i >= MAX_VARINT_LEN || (group << shift) >> shift != group {
+            return Err(DecodeError::Overflow);
+        }
+        id |= group << shift;
+        if byte & VARINT_CONTINUATION == 0 {
+            return Ok((id, i + 1));
+        }
+    }
+    Err(DecodeError::Truncated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_snowflake;
+
+    #[test]
+    fn sortable_keys_sort_like_snowflakes() {
+        let generator = crate::SnowflakeGenerator::new(0);
+        let mut ids: Vec<_> = (0..1_000)
+            .map(|i| {
+                if i % 100 == 0 {
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                }
+                generator.generate::<0>(0)
+            })
+            .chain([0, 1, u64::MAX as u128, u128::MAX])
+            .collect();
+        let mut keys: Vec<_> = ids.iter().map(|&id| to_sortable_key(id)).collect();
+        ids.sort_unstable();
+        keys.sort_unstable();
+        for (id, key) in ids.iter().zip(&keys) {
+            assert_eq!(key.len(), SORTABLE_KEY_LEN);
+            assert_eq!(from_sortable_key(key), Ok(*id));
+        }
+        assert_eq!(
+            from_sortable_key(&keys[10].to_ascii_lowercase()),
+            Ok(ids[10])
+        );
+    }
+
+    #[test]
+    fn malformed_sortable_keys_error() {
+        assert_eq!(from_sortable_key(""), Err(DecodeError::Empty));
+        assert_eq!(from_sortable_key("1A"), Err(DecodeError::InvalidLength));
+        assert_eq!(
+            from_sortable_key("0000000000000000000000001U"),
+            Err(DecodeError::InvalidCharacter('U'))
+        );
+        // the first digit only has room for 3 bits
+        assert_eq!(
+            from_sortable_key("80000000000000000000000000"),
+            Err(DecodeError::Overflow)
+        );
+    }
+
+    #[test]
+    fn varint_round_trip() {
+        for id in [
+            0,
+            1,
+            127,
+            128,
+            u64::MAX as u128,
+            u128::MAX,
+            generate_snowflake::<0>(0, 0),
+        ] {
+            let encoded = to_varint(id);
+            assert_eq!(from_varint(&encoded), Ok((id, encoded.len())));
+        }
+        assert_eq!(to_varint(0), [0]);
+        assert_eq!(to_varint(127).len(), 1);
+        assert_eq!(to_varint(128).len(), 2);
+        assert_eq!(to_varint(u128::MAX).len(), MAX_VARINT_LEN);
+
+        // early snowflakes have smaller timestamps, which leaves more leading zeros to drop
+        let early = crate::pack_with_counter::<0>(1, 0, 0, 0);
+        let late = crate::pack_with_counter::<0>(u64::MAX as u128, 0, 0, 0);
+        assert!(to_varint(early).len() < to_varint(late).len());
+    }
+
+    #[test]
+    fn truncated_varint_errors() {
+        assert_eq!(from_varint(&[]), Err(DecodeError::Empty));
+        let encoded = to_varint(u128::MAX);
+        for len in 1..encoded.len() {
+            assert_eq!(from_varint(&encoded[..len]), Err(DecodeError::Truncated));
+        }
+    }
+
+    #[test]
+    fn oversized_varint_errors() {
+        // one bit past u128::MAX in the last group
+        let mut encoded = to_varint(u128::MAX);
+        *encoded.last_mut().unwrap() = 0x07;
+        assert_eq!(from_varint(&encoded), Err(DecodeError::Overflow));
+        // one group too many
+        let mut encoded = vec![VARINT_CONTINUATION; MAX_VARINT_LEN];
+        encoded.push(0);
+        assert_eq!(from_varint(&encoded), Err(DecodeError::Overflow));
+    }
+
+    #[test]
+    fn checksum_round_trip() {
+        for id in [0, 1, 61, 62, u128::MAX, generate_snowflake::<0>(0, 0)] {
+            let encoded = encode_base62_with_checksum(id);
+            assert_eq!(decode_base62_with_checksum(&encoded), Ok(id));
+        }
+    }
+
+    #[test]
+    fn checksum_detects_substitution() {
+        let encoded = encode_base62_with_checksum(generate_snowflake::<0>(0, 0));
+        let mut bytes = encoded.into_bytes();
+        bytes[3] = if bytes[3] == b'A' { b'B' } else { b'A' };
+        let corrupted = String::from_utf8(bytes).unwrap();
+        assert_eq!(
+            decode_base62_with_checksum(&corrupted),
+            Err(DecodeError::ChecksumMismatch)
+        );
+    }
+
+    #[test]
+    fn checksum_detects_transposition() {
+        let encoded = encode_base62_with_checksum(0x0123_4567_89AB_CDEF_u128);
+        let mut bytes = encoded.into_bytes();
+        let i = (0..bytes.len() - 2)
+            .find(|&i| bytes[i] != bytes[i + 1])
+            .unwrap();
+        bytes.swap(i, i + 1);
+        let transposed = String::from_utf8(bytes).unwrap();
+        assert_eq!(
+            decode_base62_with_checksum(&transposed),
+            Err(DecodeError::ChecksumMismatch)
+        );
+    }
+}
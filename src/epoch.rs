@@ -0,0 +1,97 @@
+use crate::{timestamp_of, SnowflakeError, MAX_TIMESTAMP, TIMESTAMP_SHIFT};
+
+/// Rewrites the timestamp of a snowflake generated against one epoch so that it represents the
+/// same point in time against another, leaving every other field untouched.
+///
+/// Both epochs are in milliseconds since the Unix Epoch. Rebasing a batch of snowflakes onto the
+/// same epoch preserves their ordering.
+///
+/// # Errors
+/// Returns [`SnowflakeError::TimestampUnderflow`] if the snowflake was generated before the new
+/// epoch, or [`SnowflakeError::TimestampOverflow`] if its timestamp no longer fits.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{generate_snowflake, rebase_epoch, FERRIS_EPOCH};
+/// let id = generate_snowflake::<0>(0, 0);
+/// let rebased = rebase_epoch(id, FERRIS_EPOCH, FERRIS_EPOCH - 1_000).unwrap();
+/// assert_eq!(rebase_epoch(rebased, FERRIS_EPOCH - 1_000, FERRIS_EPOCH), Ok(id));
+/// ```
+pub fn rebase_epoch(
+    id: u128,
+    old_epoch_unix_ms: u128,
+    new_epoch_unix_ms: u128,
+) -> Result<u128, SnowflakeError> {
+    let unix_ms = timestamp_of(id)
+        .checked_add(old_epoch_unix_ms)
+        .ok_or(SnowflakeError::TimestampOverflow)?;
+    let timestamp = unix_ms
+        .checked_sub(new_epoch_unix_ms)
+        .ok_or(SnowflakeError::TimestampUnderflow)?;
+    if timestamp > MAX_TIMESTAMP {
+        return Err(SnowflakeError::TimestampOverflow);
+    }
+    let other_fields = id & ((1 << TIMESTAMP_SHIFT) - 1);
+    Ok((timestamp << TIMESTAMP_SHIFT) | other_fields)
+}
+
+/// Rebases every snowflake in `ids` with [`rebase_epoch`].
+///
+/// # Errors
+/// Returns the first error [`rebase_epoch`] returns for any of the snowflakes.
+pub fn rebase_epoch_many(
+    ids: &[u128],
+    old_epoch_unix_ms: u128,
+    new_epoch_unix_ms: u128,
+) -> Result<Vec<u128>, SnowflakeError> {
+    ids.iter()
+        .map(|&id| rebase_epoch(id, old_epoch_unix_ms, new_epoch_unix_ms))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        decode_snowflake, generate_snowflake, pack_with_counter, unix_timestamp_ms_of, FERRIS_EPOCH,
+    };
+
+    /// 01/01/2024 00:00:00.0000+00:00
+    const COMPANY_EPOCH: u128 = 1_704_067_200_000;
+
+    #[test]
+    fn rebased_snowflakes_keep_their_absolute_time() {
+        let ids: Vec<_> = (0..100).map(|_| generate_snowflake::<3>(4, 5)).collect();
+        let rebased = rebase_epoch_many(&ids, FERRIS_EPOCH, COMPANY_EPOCH).unwrap();
+        for (&id, &rebased) in ids.iter().zip(&rebased) {
+            assert_eq!(
+                timestamp_of(rebased) + COMPANY_EPOCH,
+                unix_timestamp_ms_of(id)
+            );
+            let (parts, rebased_parts) = (decode_snowflake(id), decode_snowflake(rebased));
+            assert_eq!(rebased_parts.counter, parts.counter);
+            assert_eq!(rebased_parts.node_id, parts.node_id);
+        }
+        let mut sorted = ids.clone();
+        sorted.sort_unstable();
+        let mut rebased_sorted = rebased.clone();
+        rebased_sorted.sort_unstable();
+        assert_eq!(
+            rebase_epoch_many(&sorted, FERRIS_EPOCH, COMPANY_EPOCH).unwrap(),
+            rebased_sorted
+        );
+    }
+
+    #[test]
+    fn rebasing_before_the_new_epoch_errors() {
+        let id = pack_with_counter::<0>(0, 0, 0, 0);
+        assert_eq!(
+            rebase_epoch(id, FERRIS_EPOCH, COMPANY_EPOCH),
+            Err(SnowflakeError::TimestampUnderflow)
+        );
+        assert_eq!(
+            rebase_epoch_many(&[id], FERRIS_EPOCH, COMPANY_EPOCH),
+            Err(SnowflakeError::TimestampUnderflow)
+        );
+    }
+}
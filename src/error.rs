@@ -0,0 +1,126 @@
+use std::fmt;
+
+/// An error returned when a string or byte representation of a snowflake can't be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DecodeError {
+    /// The input was empty.
+    Empty,
+    /// The input contained a character outside of the encoding's alphabet.
+    InvalidCharacter(char),
+    /// The decoded value doesn't fit in a `u128`.
+    Overflow,
+    /// The check character didn't match the rest of the input.
+    ChecksumMismatch,
+    /// The input ended in the middle of an encoded value.
+    Truncated,
+    /// The input wasn't the fixed length the encoding requires.
+    InvalidLength,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("input was empty"),
+            Self::InvalidCharacter(c) => write!(f, "invalid character {:?} in input", c),
+            Self::Overflow => f.write_str("decoded value does not fit in a u128"),
+            Self::ChecksumMismatch => f.write_str("check character does not match input"),
+            Self::Truncated => f.write_str("input ended in the middle of a value"),
+            Self::InvalidLength => f.write_str("input is not the required length"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// An error returned when a snowflake can't be generated or manipulated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SnowflakeError {
+    /// A [`NodeIdProvider`](crate::NodeIdProvider) had no free node IDs left to hand out.
+    NodeIdsExhausted,
+    /// A value was too large for the reserved bits of a snowflake.
+    ReservedOverflow,
+    /// A timestamp was too large for the timestamp field of a snowflake.
+    TimestampOverflow,
+    /// A timestamp would have been before the epoch it's relative to.
+    TimestampUnderflow,
+    /// The clock didn't advance enough to generate a snowflake later than the last one.
+    NoProgress,
+    /// A generator in strict epoch mode was asked for a snowflake before its epoch.
+    EpochNotReached {
+        /// How long until the epoch is reached, in milliseconds.
+        remaining_ms: u128,
+    },
+    /// The global generator was already configured or used.
+    GlobalAlreadyInitialized,
+    /// More snowflakes were generated in one millisecond than the counter can tell apart.
+    CounterExhausted,
+    /// A node ID was too large for the node field of a snowflake.
+    NodeIdOverflow,
+    /// A counter was too large for the counter field of a snowflake.
+    CounterOverflow,
+    /// A shard was too large for the shard field of a snowflake.
+    ShardOverflow,
+    /// A format version was too large for the format version field of a snowflake.
+    FormatVersionOverflow,
+}
+
+impl fmt::Display for SnowflakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NodeIdsExhausted => f.write_str("no free node IDs are left to acquire"),
+            Self::ReservedOverflow => f.write_str("value does not fit in the reserved bits"),
+            Self::TimestampOverflow => f.write_str("timestamp does not fit in a snowflake"),
+            Self::TimestampUnderflow => f.write_str("timestamp is before the epoch"),
+            Self::NoProgress => {
+                f.write_str("clock did not advance enough to generate a later snowflake")
+            }
+            Self::EpochNotReached { remaining_ms } => {
+                write!(
+                    f,
+                    "epoch will not be reached for another {}ms",
+                    remaining_ms
+                )
+            }
+            Self::GlobalAlreadyInitialized => {
+                f.write_str("global generator was already configured or used")
+            }
+            Self::CounterExhausted => {
+                f.write_str("counter is exhausted for the current millisecond")
+            }
+            Self::NodeIdOverflow => f.write_str("node ID does not fit in a snowflake"),
+            Self::CounterOverflow => f.write_str("counter does not fit in a snowflake"),
+            Self::ShardOverflow => f.write_str("shard does not fit in a snowflake"),
+            Self::FormatVersionOverflow => {
+                f.write_str("format version does not fit in a snowflake")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnowflakeError {}
+
+/// An error returned by [`verify_monotonic`](crate::verify_monotonic) when a snowflake isn't
+/// chronologically later than the one before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonotonicityViolation {
+    /// The index of the offending snowflake.
+    pub index: usize,
+    /// The snowflake before the offending one.
+    pub previous: u128,
+    /// The offending snowflake.
+    pub current: u128,
+}
+
+impl fmt::Display for MonotonicityViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "snowflake {} at index {} is not later than the snowflake {} before it",
+            self.current, self.index, self.previous
+        )
+    }
+}
+
+impl std::error::Error for MonotonicityViolation {}
@@ -0,0 +1,1419 @@
+#[cfg(not(feature = "wasm"))]
+use crate::clock::CachedClock;
+use crate::clock::Stopwatch;
+use crate::cluster::ClusterClock;
+use crate::collision::CollisionDetector;
+use crate::history::EventHistory;
+use crate::seed::{counter_seed, entropy_seed};
+use crate::window::Window;
+use crate::{
+    debug_assert_unused_clear, timestamp_of, Clock, Counter, CounterPolicy, NodeIdProvider,
+    RequestScope, SnowflakeError, SnowflakeEvent, SystemClock, FERRIS_EPOCH, MODEL_TYPE_SHIFT,
+};
+use std::cmp::Ordering as CmpOrdering;
+use std::io;
+use std::sync::atomic::{AtomicU16, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// How long [`SnowflakeGenerator::generate_checked_unique`] waits for the clock before giving up.
+const PROGRESS_TIMEOUT: Duration = Duration::from_millis(100);
+/// How many consecutive milliseconds the counter must be exhausted in before
+/// [`CounterPolicy::AdaptiveSpin`] starts yielding.
+const ADAPTIVE_YIELD_STREAK: u32 = 3;
+
+/// A snowflake generator bound to a single node, with its own internal counter.
+///
+/// Unlike [`generate_snowflake`](crate::generate_snowflake), which shares one counter between
+/// every caller in the process, each generator counts independently. The counter is an
+/// [`AtomicU16`] by default, but any [`Counter`] can be used to trade the layout for more
+/// snowflakes per millisecond with [`with_counter`](Self::with_counter).
+///
+/// Snowflakes from generators with any other counter width are laid out differently, so they
+/// must not go through the free decoding functions: decode them with the [`Counter`]'s own
+/// [`counter_of`](Counter::counter_of), [`api_version_of`](Counter::api_version_of) and
+/// [`node_id_of`](Counter::node_id_of) instead.
+///
+/// # Sharing
+/// Everything that changes while generating is kept in atomics (or, for
+/// [`generate_checked_unique`](Self::generate_checked_unique), behind a lock), so every method
+/// used after configuration takes `&self`. To share one generator between threads or tasks,
+/// configure it and then wrap it in an [`Arc`](std::sync::Arc):
+/// ```rust
+/// use ferrischat_snowflake_generator::SnowflakeGenerator;
+/// use std::sync::Arc;
+/// let generator = Arc::new(SnowflakeGenerator::new(0));
+/// let handle = {
+///     let generator = Arc::clone(&generator);
+///     std::thread::spawn(move || generator.generate::<0>(0))
+/// };
+/// assert_ne!(generator.generate::<0>(0), handle.join().unwrap());
+/// ```
+pub struct SnowflakeGenerator<C: Counter = AtomicU16> {
+    /// The node this generator stamps onto every snowflake, which can be swapped at runtime.
+    node_id: AtomicU16,
+    /// How many low bits of the node field hold `pid` rather than `node_id`.
+    pid_bits: u32,
+    /// The process ID folded into `pid_bits` bits.
+    pid: u16,
+    /// The epoch this generator's timestamps count from, in milliseconds since the Unix Epoch.
+    epoch: u128,
+    /// Whether to refuse to generate snowflakes before `epoch`.
+    strict_epoch: bool,
+    /// The internal counter for snowflakes generated by this generator.
+    counter: C,
+    /// Where the node ID was acquired from, if anywhere, and which one it was, so it can be
+    /// released on drop.
+    provider: Option<(Box<dyn NodeIdProvider>, u16)>,
+    /// A cluster-wide clock this generator keeps its timestamps from falling behind, if any.
+    cluster_clock: Option<ClusterClock>,
+    /// The earliest time snowflakes are stamped with, in milliseconds since the Unix Epoch.
+    min_timestamp: u128,
+    /// The earliest timestamp snowflakes are stamped with since the last
+    /// [`barrier`](Self::barrier), in milliseconds since the generator's epoch.
+    barrier_floor: AtomicU64,
+    /// Where this generator reads the current time from.
+    clock: Box<dyn Clock>,
+    /// Remembers recently generated snowflakes to catch duplicates, if enabled.
+    collision_detector: Option<CollisionDetector>,
+    /// Remembers recent generation events for post-mortem inspection, if enabled.
+    event_history: Option<EventHistory>,
+    /// Counts how many snowflakes were generated in the current millisecond.
+    window: Window,
+    /// What to do when `window` counts more snowflakes than the counter can tell apart.
+    counter_policy: CounterPolicy,
+    /// The last millisecond the counter was exhausted in.
+    last_exhausted: AtomicU64,
+    /// How many consecutive milliseconds up to `last_exhausted` the counter was exhausted in.
+    exhausted_streak: AtomicU32,
+    /// How many snowflakes can be generated in one millisecond before warning, or `u32::MAX`.
+    counter_warn_threshold: AtomicU32,
+    /// Called when `counter_warn_threshold` is exceeded with the millisecond and the count.
+    counter_warn_hook: Box<dyn Fn(u128, u32) + Send + Sync>,
+    /// Called every time the counter wraps back around to 0.
+    on_counter_wrap: Box<dyn Fn() + Send + Sync>,
+    /// The last snowflake issued by `generate_checked_unique`.
+    last_checked: Mutex<Option<u128>>,
+    #[cfg(feature = "time-safety-checks")]
+    /// The timestamp of the last snowflake generated.
+    last_time: AtomicU64,
+    #[cfg(feature = "time-safety-checks")]
+    /// Clock rollbacks up to this many microseconds are waited out by spinning instead of sleeping.
+    spin_threshold_us: u64,
+}
+
+impl SnowflakeGenerator {
+    /// Creates a generator for a manually configured node ID.
+    #[inline]
+    #[must_use]
+    pub fn new(node_id: u16) -> Self {
+        Self::with_counter(node_id, AtomicU16::new(0))
+    }
+
+    /// Creates a generator whose node ID is acquired from `provider`.
+    ///
+    /// The node ID is released back to `provider` when the generator is dropped.
+    ///
+    /// # Errors
+    /// Returns any error `provider` returns while acquiring a node ID.
+    pub fn with_provider<P: NodeIdProvider + 'static>(provider: P) -> Result<Self, SnowflakeError> {
+        let node_id = provider.acquire()?;
+        let mut generator = Self::new(node_id);
+        generator.provider = Some((Box::new(provider), node_id));
+        Ok(generator)
+    }
+}
+
+impl<C: Counter> SnowflakeGenerator<C> {
+    /// Creates a generator for a manually configured node ID that counts with `counter`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use ferrischat_snowflake_generator::SnowflakeGenerator;
+    /// use std::sync::atomic::AtomicU32;
+    /// let generator = SnowflakeGenerator::with_counter(0, AtomicU32::new(0));
+    /// assert_ne!(generator.generate::<0>(0), generator.generate::<0>(0));
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_counter(node_id: u16, counter: C) -> Self {
+        Self {
+            node_id: AtomicU16::new(node_id),
+            pid_bits: 0,
+            pid: 0,
+            epoch: FERRIS_EPOCH,
+            strict_epoch: false,
+            counter,
+            provider: None,
+            cluster_clock: None,
+            min_timestamp: 0,
+            barrier_floor: AtomicU64::new(0),
+            clock: Box::new(SystemClock),
+            collision_detector: None,
+            event_history: None,
+            window: Window::new(),
+            counter_policy: CounterPolicy::Wrap,
+            last_exhausted: AtomicU64::new(0),
+            exhausted_streak: AtomicU32::new(0),
+            counter_warn_threshold: AtomicU32::new(u32::MAX),
+            counter_warn_hook: Box::new(|timestamp, count| {
+                eprintln!(
+                    "generated {} snowflakes in millisecond {}, the counter is close to wrapping",
+                    count, timestamp
+                );
+            }),
+            on_counter_wrap: Box::new(|| {}),
+            last_checked: Mutex::new(None),
+            #[cfg(feature = "time-safety-checks")]
+            last_time: AtomicU64::new(0),
+            #[cfg(feature = "time-safety-checks")]
+            spin_threshold_us: 0,
+        }
+    }
+
+    /// Keeps this generator's timestamps from falling behind a logical cluster clock, so that
+    /// snowflakes stay globally ordered across nodes whose clocks are skewed.
+    ///
+    /// `now` returns the authoritative cluster time in milliseconds since the Unix Epoch. It's
+    /// consulted about once a second, and snowflakes are stamped with `max(local, cluster)`
+    /// based on how far ahead the cluster clock was at the last sync. The adjustment never
+    /// shrinks, so a generator's timestamps never go backwards even if the cluster clock does.
+    ///
+    /// This is only eventually consistent: between syncs, ordering across nodes relies on their
+    /// local clocks advancing at the same rate, and a node that hasn't synced yet may still lag
+    /// behind the rest of the cluster.
+    #[must_use]
+    pub fn with_cluster_clock(mut self, now: impl Fn() -> u128 + Send + Sync + 'static) -> Self {
+        self.cluster_clock = Some(ClusterClock::new(now));
+        self
+    }
+
+    /// Counts timestamps from `epoch_unix_ms` milliseconds since the Unix Epoch instead of the
+    /// Ferris Epoch.
+    ///
+    /// Snowflakes generated before the epoch all have a timestamp of 0, unless
+    /// [`with_strict_epoch`](Self::with_strict_epoch) is used. Functions that convert timestamps
+    /// back to wall-clock time, such as [`unix_timestamp_ms_of`](crate::unix_timestamp_ms_of),
+    /// assume the Ferris Epoch: use [`rebase_epoch`](crate::rebase_epoch) to convert between
+    /// them.
+    #[must_use]
+    pub fn with_epoch(mut self, epoch_unix_ms: u128) -> Self {
+        self.epoch = epoch_unix_ms;
+        self
+    }
+
+    /// Never stamps snowflakes with a time before `unix_ms` milliseconds since the Unix Epoch,
+    /// such as the time the cluster was deployed, stamping them with `max(clock, unix_ms)`
+    /// instead.
+    ///
+    /// This keeps snowflakes generated while a freshly booted node's clock is still wrong from
+    /// sorting before older records once the clock is corrected. Until the clock reaches the
+    /// floor every snowflake shares one timestamp, so only the counter tells them apart: with a
+    /// policy that waits for the next millisecond, such as [`CounterPolicy::SpinToNextMs`],
+    /// exhausting the counter blocks until the clock passes the floor.
+    #[must_use]
+    pub fn with_min_timestamp(mut self, unix_ms: u128) -> Self {
+        self.min_timestamp = unix_ms;
+        self
+    }
+
+    /// Refuses to generate snowflakes until the wall clock has reached this generator's epoch,
+    /// rather than generating snowflakes that all have a timestamp of 0.
+    ///
+    /// This matters for epochs set in the future with [`with_epoch`](Self::with_epoch): use
+    /// [`try_generate`](Self::try_generate) to find out how long is left, since
+    /// [`generate`](Self::generate) panics instead.
+    #[must_use]
+    pub fn with_strict_epoch(mut self) -> Self {
+        self.strict_epoch = true;
+        self
+    }
+
+    /// Starts the counter at a random value instead of 0, so that generators which share a node
+    /// ID and restart within the same millisecond are less likely to hand out the same
+    /// snowflakes.
+    ///
+    /// The seed is read from the operating system's entropy source, `/dev/urandom`. If that
+    /// fails, as it can in locked-down environments, the seed is derived from the current time in
+    /// nanoseconds and the process ID instead, which is good enough to tell restarts apart but is
+    /// easy to guess and may repeat between processes started at the same instant on different
+    /// hosts. Which of the two was used is logged to stderr.
+    ///
+    /// Windows has no `/dev/urandom`, so it always takes the time-based fallback. So does
+    /// WebAssembly, where the process ID isn't available either and the seed comes from the time
+    /// alone.
+    #[must_use]
+    pub fn with_random_counter_seed(self) -> Self {
+        self.with_counter_seed_from(entropy_seed)
+    }
+
+    /// Like [`with_random_counter_seed`](Self::with_random_counter_seed), with the entropy
+    /// source given.
+    fn with_counter_seed_from(mut self, entropy: impl FnOnce() -> io::Result<u32>) -> Self {
+        self.counter = C::from_seed(counter_seed(entropy));
+        self
+    }
+
+    /// Splits the node field between the node ID and the current process ID, so that several
+    /// processes on one host can share a node ID without colliding.
+    ///
+    /// The low `bits` bits of the node field (bits `112 - bits` to 111) hold the process ID,
+    /// folded into `bits` bits by XORing its `bits`-wide chunks together, and the node ID is
+    /// shifted up into the remaining `16 - bits` bits, which limits it to below `2^(16 - bits)`.
+    /// [`node_id_of`](crate::node_id_of) returns both together.
+    ///
+    /// Processes whose IDs fold to the same value still collide, so use enough bits to cover the
+    /// number of processes sharing a node.
+    ///
+    /// # Panics
+    /// Panics if `bits` is more than 16, or if the node ID doesn't fit in `16 - bits` bits. Also
+    /// panics on `wasm32-unknown-unknown`, which has no process IDs.
+    #[must_use]
+    pub fn with_pid_component(self, bits: u32) -> Self {
+        self.with_pid_component_from(bits, std::process::id())
+    }
+
+    /// Like [`with_pid_component`](Self::with_pid_component), with the process ID given.
+    fn with_pid_component_from(mut self, bits: u32, pid: u32) -> Self {
+        assert!(bits <= 16, "the node field is only 16 bits wide");
+        assert_node_id_fits(self.node_id(), bits);
+        self.pid_bits = bits;
+        self.pid = fold(pid, bits) as u16;
+        self
+    }
+
+    /// Reads the current time from `clock` instead of the system clock.
+    #[must_use]
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Reads the clock on a background thread every `refresh_interval` instead of on every
+    /// generation, trading a little timestamp staleness for far fewer clock reads (each of which
+    /// is usually a syscall).
+    ///
+    /// This wraps whichever clock the generator was using, so call it after
+    /// [`with_clock`](Self::with_clock). Note that the background thread doesn't see a clock
+    /// frozen with `with_frozen_time` on another thread.
+    ///
+    /// Timestamps can lag behind the clock by up to `refresh_interval`, plus however long the
+    /// background thread is kept from running. Every generation in between shares one timestamp,
+    /// so an interval longer than a millisecond makes each timestamp last longer and uses up the
+    /// counter faster: keep it well below a millisecond (around 250µs) under heavy load. The
+    /// background thread is stopped when the generator is dropped.
+    ///
+    /// This is not available with the `wasm` feature, since it needs threads.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use ferrischat_snowflake_generator::SnowflakeGenerator;
+    /// use std::time::Duration;
+    /// let generator = SnowflakeGenerator::new(0).with_cached_clock(Duration::from_micros(250));
+    /// assert_ne!(generator.generate::<0>(0), generator.generate::<0>(0));
+    /// ```
+    #[cfg(not(feature = "wasm"))]
+    #[must_use]
+    pub fn with_cached_clock(mut self, refresh_interval: Duration) -> Self {
+        let clock = std::mem::replace(&mut self.clock, Box::new(SystemClock));
+        self.clock = Box::new(CachedClock::new(clock, refresh_interval));
+        self
+    }
+
+    /// Remembers the last `capacity` snowflakes generated and panics if one is ever generated
+    /// twice.
+    ///
+    /// This is a safety net for catching logic bugs (such as a custom clock getting stuck) in
+    /// critical pipelines, at the cost of a lock on every generation and roughly 40 bytes of
+    /// memory per remembered snowflake.
+    #[must_use]
+    pub fn with_collision_detection(mut self, capacity: usize) -> Self {
+        self.collision_detector = Some(CollisionDetector::new(capacity));
+        self
+    }
+
+    /// Remembers the last `capacity` generation events, for inspecting with
+    /// [`recent_events`](Self::recent_events) after an incident.
+    ///
+    /// This is a debugging aid: it costs a few atomic operations on every generation, but never a
+    /// lock, and 24 bytes of memory per remembered event, all allocated up front.
+    #[must_use]
+    pub fn with_event_history(mut self, capacity: usize) -> Self {
+        self.event_history = Some(EventHistory::new(capacity));
+        self
+    }
+
+    /// Returns the most recent generation events, oldest first, or nothing if
+    /// [`with_event_history`](Self::with_event_history) wasn't used.
+    pub fn recent_events(&self) -> Vec<SnowflakeEvent> {
+        self.event_history
+            .as_ref()
+            .map_or_else(Vec::new, EventHistory::events)
+    }
+
+    /// Replaces what happens when the counter warning threshold is exceeded, which by default is
+    /// printing a warning to stderr.
+    ///
+    /// `hook` is called with the millisecond the threshold was exceeded in and how many snowflakes
+    /// had been generated in it so far. It runs on the generation path, so it should be cheap.
+    #[must_use]
+    pub fn with_counter_warn_hook(
+        mut self,
+        hook: impl Fn(u128, u32) + Send + Sync + 'static,
+    ) -> Self {
+        self.counter_warn_hook = Box::new(hook);
+        self
+    }
+
+    /// Calls `callback` every time the counter wraps back around to 0, exactly once per wrap. By
+    /// default nothing happens.
+    ///
+    /// This is a chance to react to sustained load, such as by triggering an autoscale event.
+    /// `callback` runs on the generation path, so it must be cheap and must not block: hand
+    /// anything heavier off to another thread.
+    #[must_use]
+    pub fn with_counter_wrap_hook(mut self, callback: impl Fn() + Send + Sync + 'static) -> Self {
+        self.on_counter_wrap = Box::new(callback);
+        self
+    }
+
+    /// Chooses what happens when more snowflakes are generated in one millisecond than the
+    /// counter can tell apart. Defaults to [`CounterPolicy::Wrap`].
+    ///
+    /// # Examples
+    /// ```rust
+    /// use ferrischat_snowflake_generator::{CounterPolicy, SnowflakeGenerator};
+    /// let generator = SnowflakeGenerator::new(0).with_counter_policy(CounterPolicy::AdaptiveSpin);
+    /// assert_ne!(generator.generate::<0>(0), generator.generate::<0>(0));
+    /// ```
+    #[must_use]
+    pub fn with_counter_policy(mut self, policy: CounterPolicy) -> Self {
+        self.counter_policy = policy;
+        self
+    }
+
+    /// Warns once per millisecond when more than `threshold` snowflakes are generated in it.
+    ///
+    /// The default counter wraps after 65,536 snowflakes in one millisecond, at which point
+    /// snowflakes start colliding, so a threshold of around 90% of that (58,982) gives early
+    /// warning of sustained load before it becomes a problem. Warnings are off until this is
+    /// called.
+    #[inline]
+    pub fn set_counter_warn_threshold(&self, threshold: u16) {
+        self.counter_warn_threshold
+            .store(threshold as u32, Ordering::Relaxed);
+    }
+
+    #[cfg(feature = "time-safety-checks")]
+    /// Waits out clock rollbacks of up to `spin_threshold_us` microseconds by busy-spinning until
+    /// the clock catches up, rather than sleeping.
+    ///
+    /// Sleeping has millisecond granularity, so it over-sleeps tiny rollbacks; spinning recovers
+    /// from them as soon as possible, at the cost of keeping a core busy while it waits. Larger
+    /// rollbacks are still slept through. Defaults to 0, which always sleeps.
+    ///
+    /// Clocks only report whole milliseconds, so rollbacks are measured in whole milliseconds
+    /// too: a threshold below 1,000 behaves exactly like 0, and any other threshold is effectively
+    /// rounded down to a multiple of 1,000.
+    #[must_use]
+    pub fn with_spin_threshold_us(mut self, spin_threshold_us: u64) -> Self {
+        self.spin_threshold_us = spin_threshold_us;
+        self
+    }
+
+    /// Returns the node ID this generator stamps onto every snowflake.
+    ///
+    /// With [`with_pid_component`](Self::with_pid_component), this is the node ID before the
+    /// process ID is added to it.
+    #[inline]
+    #[must_use]
+    pub fn node_id(&self) -> u16 {
+        self.node_id.load(Ordering::Relaxed)
+    }
+
+    /// Swaps the node ID this generator stamps onto every snowflake, without interrupting
+    /// generation on other threads.
+    ///
+    /// Every snowflake is stamped with either the old or the new node ID, so snowflakes generated
+    /// around the swap carry different node fields, which doesn't affect their uniqueness. A
+    /// generator created with [`with_provider`](SnowflakeGenerator::with_provider) still releases
+    /// the node ID it acquired when it's dropped.
+    ///
+    /// # Panics
+    /// Panics if [`with_pid_component`](Self::with_pid_component) left too few bits for the node
+    /// ID.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use ferrischat_snowflake_generator::{node_id_of, SnowflakeGenerator};
+    /// let generator = SnowflakeGenerator::new(1);
+    /// generator.set_node_id(2);
+    /// assert_eq!(node_id_of(generator.generate::<0>(0)), 2);
+    /// ```
+    #[inline]
+    pub fn set_node_id(&self, node_id: u16) {
+        assert_node_id_fits(node_id, self.pid_bits);
+        self.node_id.store(node_id, Ordering::Relaxed);
+    }
+
+    /// Returns the node field to stamp onto every snowflake.
+    #[inline]
+    fn node_field(&self) -> u16 {
+        ((self.node_id() as u32) << self.pid_bits) as u16 | self.pid
+    }
+
+    /// Generates a snowflake from the current API version and the model type.
+    ///
+    /// # Panics
+    /// Panics if the current time is behind the Unix Epoch, if collision detection is enabled
+    /// and the snowflake was already generated, if strict epoch mode is on and the epoch hasn't
+    /// been reached yet, or if the counter policy is [`CounterPolicy::Error`] and the counter is
+    /// exhausted.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use ferrischat_snowflake_generator::SnowflakeGenerator;
+    /// let generator = SnowflakeGenerator::new(0);
+    /// assert_ne!(generator.generate::<0>(0), generator.generate::<0>(0));
+    /// ```
+    #[inline]
+    pub fn generate<const API_VERSION: u8>(&self, model_type: u8) -> u128 {
+        match self.try_generate::<API_VERSION>(model_type) {
+            Ok(id) => id,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Generates a snowflake from the current API version and the model type, like
+    /// [`generate`](Self::generate).
+    ///
+    /// # Errors
+    /// Returns [`SnowflakeError::EpochNotReached`] if strict epoch mode is on and the epoch
+    /// hasn't been reached yet, or [`SnowflakeError::CounterExhausted`] if the counter policy is
+    /// [`CounterPolicy::Error`] and the counter is exhausted.
+    ///
+    /// # Panics
+    /// Panics if the current time is behind the Unix Epoch, or if collision detection is
+    /// enabled and the snowflake was already generated.
+    #[inline]
+    pub fn try_generate<const API_VERSION: u8>(
+        &self,
+        model_type: u8,
+    ) -> Result<u128, SnowflakeError> {
+        self.check_epoch()?;
+        self.generate_unchecked::<API_VERSION>(model_type, self.counter_policy)
+    }
+
+    /// Generates a snowflake from the current API version and the model type, or returns `None`
+    /// if the counter is exhausted for the current millisecond, whatever the counter policy.
+    ///
+    /// This never wraps and never blocks, which lets callers apply backpressure instead of
+    /// exceeding the safe generation rate. It also returns `None` if strict epoch mode is on and
+    /// the epoch hasn't been reached yet.
+    ///
+    /// # Panics
+    /// Panics if the current time is behind the Unix Epoch, or if collision detection is
+    /// enabled and the snowflake was already generated.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use ferrischat_snowflake_generator::SnowflakeGenerator;
+    /// let generator = SnowflakeGenerator::new(0);
+    /// while generator.saturating_generate::<0>(0).is_none() {
+    ///     // back off
+    /// }
+    /// ```
+    #[inline]
+    pub fn saturating_generate<const API_VERSION: u8>(&self, model_type: u8) -> Option<u128> {
+        self.check_epoch().ok()?;
+        self.generate_unchecked::<API_VERSION>(model_type, CounterPolicy::Error)
+            .ok()
+    }
+
+    /// Generates a snowflake with the given counter policy, without checking whether the epoch
+    /// has been reached.
+    #[inline]
+    fn generate_unchecked<const API_VERSION: u8>(
+        &self,
+        model_type: u8,
+        counter_policy: CounterPolicy,
+    ) -> Result<u128, SnowflakeError> {
+        let (mut timestamp, rolled_back) = self.read_timestamp();
+        let mut count = self.window.record(timestamp);
+        while u64::from(count) > 1 << C::BITS {
+            match counter_policy {
+                CounterPolicy::Wrap => break,
+                CounterPolicy::Error => return Err(SnowflakeError::CounterExhausted),
+                CounterPolicy::SpinToNextMs | CounterPolicy::AdaptiveSpin => {
+                    timestamp = self.wait_for_next_ms(timestamp);
+                    count = self.window.record(timestamp);
+                }
+            }
+        }
+        // only the snowflake that crosses the threshold warns, so this happens once per millisecond
+        if count.wrapping_sub(1) == self.counter_warn_threshold.load(Ordering::Relaxed) {
+            (self.counter_warn_hook)(timestamp, count);
+        }
+        let counter = self.counter.fetch_increment();
+        // the increment that hands out the last counter value is the one that wraps it
+        let wrapped = u64::from(counter) == (1 << C::BITS) - 1;
+        if wrapped {
+            (self.on_counter_wrap)();
+        }
+        if let Some(event_history) = &self.event_history {
+            event_history.record(SnowflakeEvent {
+                timestamp,
+                counter,
+                rolled_back,
+                wrapped,
+            });
+        }
+        let id = C::pack(
+            timestamp,
+            model_type,
+            counter,
+            API_VERSION,
+            self.node_field(),
+        );
+        // everything below the node ID is unused, however wide the counter is
+        debug_assert_unused_clear(id, (1 << (MODEL_TYPE_SHIFT - C::BITS - 24)) - 1);
+        if let Some(collision_detector) = &self.collision_detector {
+            collision_detector.check(id);
+        }
+        Ok(id)
+    }
+
+    /// Blocks until the clock has moved past `timestamp`, in which the counter was exhausted,
+    /// returning the new time.
+    #[cold]
+    fn wait_for_next_ms(&self, timestamp: u128) -> u128 {
+        let should_yield = self.counter_policy == CounterPolicy::AdaptiveSpin
+            && self.record_exhaustion(timestamp) >= ADAPTIVE_YIELD_STREAK;
+        loop {
+            if should_yield {
+                std::thread::yield_now();
+            } else {
+                std::hint::spin_loop();
+            }
+            let next = self.timestamp();
+            if next > timestamp {
+                return next;
+            }
+        }
+    }
+
+    /// Records that the counter was exhausted in `timestamp`, returning in how many consecutive
+    /// milliseconds (up to and including this one) it has been exhausted.
+    fn record_exhaustion(&self, timestamp: u128) -> u32 {
+        let timestamp = timestamp as u64;
+        let last = self.last_exhausted.swap(timestamp, Ordering::Relaxed);
+        if last == timestamp {
+            self.exhausted_streak.load(Ordering::Relaxed)
+        } else if last + 1 == timestamp {
+            self.exhausted_streak.fetch_add(1, Ordering::Relaxed) + 1
+        } else {
+            self.exhausted_streak.store(1, Ordering::Relaxed);
+            1
+        }
+    }
+
+    /// Generates a snowflake that is guaranteed to be chronologically later than the last one
+    /// this method returned, by timestamp and then by counter.
+    ///
+    /// This is stronger than `time-safety-checks`, which only makes sure timestamps don't go
+    /// backwards: if a snowflake isn't strictly later (because the clock is still behind, or the
+    /// counter wrapped within the same millisecond), it's discarded and generation is retried,
+    /// advancing the counter and spinning until the clock catches up. Calls are serialized by a
+    /// lock, so this is slower than [`generate`](Self::generate).
+    ///
+    /// # Errors
+    /// Returns [`SnowflakeError::NoProgress`] if the clock still hasn't caught up after 100ms,
+    /// as well as any error [`try_generate`](Self::try_generate) would return.
+    pub fn generate_checked_unique<const API_VERSION: u8>(
+        &self,
+        model_type: u8,
+    ) -> Result<u128, SnowflakeError> {
+        let mut last_checked = self
+            .last_checked
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        self.check_epoch()?;
+        let stopwatch = Stopwatch::start();
+        loop {
+            let id = self.generate_unchecked::<API_VERSION>(model_type, self.counter_policy)?;
+            let is_later = match *last_checked {
+                Some(last) => Self::chronological_cmp(id, last) == CmpOrdering::Greater,
+                None => true,
+            };
+            if is_later {
+                *last_checked = Some(id);
+                return Ok(id);
+            }
+            if stopwatch.elapsed() > PROGRESS_TIMEOUT {
+                return Err(SnowflakeError::NoProgress);
+            }
+            std::hint::spin_loop();
+        }
+    }
+
+    /// Makes every snowflake this generator issues afterwards have a strictly greater timestamp
+    /// than every snowflake it issued before.
+    ///
+    /// This is for checkpoints that snowflakes must sort cleanly around. Snowflakes generated at
+    /// the same time as the barrier on other threads may land on either side of it. It never
+    /// waits for the clock: instead, snowflakes are stamped with at least the millisecond after
+    /// the latest one issued so far, until the clock catches up. That millisecond's counter fills
+    /// up like any other, and every barrier raised before the clock moves on pushes the
+    /// timestamps another millisecond ahead of it.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use ferrischat_snowflake_generator::{timestamp_of, SnowflakeGenerator};
+    /// let generator = SnowflakeGenerator::new(0);
+    /// let before = generator.generate::<0>(0);
+    /// generator.barrier();
+    /// assert!(timestamp_of(generator.generate::<0>(0)) > timestamp_of(before));
+    /// ```
+    #[inline]
+    pub fn barrier(&self) {
+        let floor = self.window.latest() + 1;
+        self.barrier_floor
+            .fetch_max(floor as u64, Ordering::Relaxed);
+    }
+
+    /// Starts a scope whose snowflakes sort in the order they're issued, even if they're
+    /// generated in the same millisecond.
+    #[inline]
+    #[must_use]
+    pub const fn request_scope(&self) -> RequestScope<'_, C> {
+        RequestScope::new(self)
+    }
+
+    /// Compares two snowflakes from this generator by timestamp, then by counter.
+    #[inline]
+    pub(crate) fn chronological_cmp(a: u128, b: u128) -> CmpOrdering {
+        timestamp_of(a)
+            .cmp(&timestamp_of(b))
+            .then_with(|| C::counter_of(a).cmp(&C::counter_of(b)))
+    }
+
+    /// Returns an error if strict epoch mode is on and the epoch hasn't been reached yet.
+    #[inline]
+    fn check_epoch(&self) -> Result<(), SnowflakeError> {
+        if self.strict_epoch {
+            let now = self.clock.now_ms();
+            if now < self.epoch {
+                return Err(SnowflakeError::EpochNotReached {
+                    remaining_ms: self.epoch - now,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the current time since this generator's epoch according to its clock.
+    #[inline]
+    fn epoch_time(&self) -> u128 {
+        self.clock.now_ms().saturating_sub(self.epoch)
+    }
+
+    /// Returns the time since this generator's epoch to stamp a new snowflake with.
+    #[inline]
+    fn timestamp(&self) -> u128 {
+        self.read_timestamp().0
+    }
+
+    /// Like [`timestamp`](Self::timestamp), also returning whether the clock was caught rolling
+    /// back, which is never the case without the `time-safety-checks` feature.
+    #[inline]
+    fn read_timestamp(&self) -> (u128, bool) {
+        #[cfg(feature = "time-safety-checks")]
+        let (timestamp, rolled_back) = {
+            let last_time = self.last_time.load(Ordering::Relaxed) as u128;
+            let mut timestamp = self.epoch_time();
+            let rolled_back = timestamp < last_time;
+            if rolled_back {
+                timestamp = self.wait_for_clock(timestamp, last_time);
+            }
+            self.last_time
+                .fetch_max(timestamp as u64, Ordering::Relaxed);
+            (timestamp, rolled_back)
+        };
+        #[cfg(not(feature = "time-safety-checks"))]
+        let (timestamp, rolled_back) = (self.epoch_time(), false);
+
+        let timestamp = match &self.cluster_clock {
+            Some(cluster_clock) => cluster_clock.adjust(timestamp, self.epoch),
+            None => timestamp,
+        };
+        let floor = self.min_timestamp.saturating_sub(self.epoch);
+        let barrier_floor = self.barrier_floor.load(Ordering::Relaxed) as u128;
+        (timestamp.max(floor).max(barrier_floor), rolled_back)
+    }
+
+    #[cfg(feature = "time-safety-checks")]
+    /// Blocks until the clock has caught up with `last_time` after rolling back to `timestamp`,
+    /// returning the new time.
+    #[cold]
+    fn wait_for_clock(&self, mut timestamp: u128, last_time: u128) -> u128 {
+        // the rollback is only known to the millisecond, see `with_spin_threshold_us`
+        if (last_time - timestamp) * 1_000 <= self.spin_threshold_us as u128 {
+            while timestamp < last_time {
+                std::hint::spin_loop();
+                timestamp = self.epoch_time();
+            }
+            return timestamp;
+        }
+        let sleep_for = last_time + 1 - timestamp;
+        eprintln!(
+            "detected system clock rolling back, not generating snowflakes for {}ms",
+            sleep_for
+        );
+        crate::clock::pause(sleep_for, || self.epoch_time());
+        self.epoch_time()
+    }
+}
+
+/// Panics if `node_id` doesn't fit next to a `pid_bits`-wide process ID in the node field.
+fn assert_node_id_fits(node_id: u16, pid_bits: u32) {
+    assert!(
+        (node_id as u32) < 1 << (16 - pid_bits),
+        "node ID {} does not fit in {} bits",
+        node_id,
+        16 - pid_bits
+    );
+}
+
+/// Folds `value` into `bits` bits by XORing its `bits`-wide chunks together.
+fn fold(mut value: u32, bits: u32) -> u32 {
+    if bits == 0 {
+        return 0;
+    }
+    let mask = u32::MAX >> (32 - bits);
+    let mut folded = 0;
+    while value != 0 {
+        folded ^= value & mask;
+        value = value.checked_shr(bits).unwrap_or(0);
+    }
+    folded
+}
+
+impl<C: Counter> Default for SnowflakeGenerator<C> {
+    /// Creates a generator for node 0.
+    ///
+    /// Every process using the default generator shares node 0, so their snowflakes may collide:
+    /// production code should configure a node ID explicitly.
+    #[inline]
+    fn default() -> Self {
+        Self::with_counter(0, C::default())
+    }
+}
+
+impl<C: Counter> Drop for SnowflakeGenerator<C> {
+    fn drop(&mut self) {
+        if let Some((provider, node_id)) = &self.provider {
+            provider.release(*node_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api_version_of, chronological_cmp, counter_of, generate_snowflake, get_epoch_time,
+        model_type_of, node_id_of, with_frozen_time, without_counter,
+    };
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicU64, AtomicU8};
+    use std::sync::Arc;
+
+    const HOUR_MS: u128 = 60 * 60 * 1_000;
+
+    /// Returns a clock that reads `start` milliseconds after the Ferris Epoch until the returned
+    /// handle is used to move it.
+    fn mock_clock(start: u64) -> (Arc<AtomicU64>, impl Clock) {
+        let now = Arc::new(AtomicU64::new(start));
+        let clock_now = Arc::clone(&now);
+        let clock = move || FERRIS_EPOCH + clock_now.load(Ordering::Relaxed) as u128;
+        (now, clock)
+    }
+
+    /// Returns a clock whose `n`th read is `script(n)` milliseconds after the Ferris Epoch, along
+    /// with how many times it has been read.
+    fn scripted_clock(
+        script: impl Fn(u64) -> u64 + Send + Sync + 'static,
+    ) -> (Arc<AtomicU64>, impl Clock) {
+        let reads = Arc::new(AtomicU64::new(0));
+        let clock_reads = Arc::clone(&reads);
+        let clock =
+            move || FERRIS_EPOCH + script(clock_reads.fetch_add(1, Ordering::Relaxed)) as u128;
+        (reads, clock)
+    }
+
+    #[test]
+    fn cluster_clock_ahead_moves_timestamps_forward() {
+        let generator = SnowflakeGenerator::new(0)
+            .with_cluster_clock(|| get_epoch_time() + FERRIS_EPOCH + HOUR_MS);
+        let before = get_epoch_time();
+        let timestamp = timestamp_of(generator.generate::<0>(0));
+        let after = get_epoch_time();
+        assert!(timestamp >= before + HOUR_MS);
+        assert!(timestamp <= after + HOUR_MS);
+    }
+
+    #[test]
+    fn cluster_clock_behind_is_ignored() {
+        let generator = SnowflakeGenerator::new(0).with_cluster_clock(|| FERRIS_EPOCH);
+        let before = get_epoch_time();
+        let timestamp = timestamp_of(generator.generate::<0>(0));
+        assert!(timestamp >= before);
+        assert!(timestamp <= get_epoch_time());
+    }
+
+    #[cfg(feature = "time-safety-checks")]
+    #[test]
+    fn small_rollback_spins_instead_of_sleeping() {
+        // the clock reads 1000, rolls back to 999 for two reads, then reads 1000 again
+        let (reads, clock) = scripted_clock(|read| match read {
+            1 | 2 => 999,
+            _ => 1_000,
+        });
+        let generator = SnowflakeGenerator::new(0)
+            .with_clock(clock)
+            .with_spin_threshold_us(1_000);
+
+        assert_eq!(timestamp_of(generator.generate::<0>(0)), 1_000);
+        assert_eq!(timestamp_of(generator.generate::<0>(0)), 1_000);
+        // sleeping reads the clock just once after waking up, which would still be rolled back
+        assert_eq!(reads.load(Ordering::Relaxed), 4);
+    }
+
+    #[cfg(all(feature = "time-safety-checks", not(feature = "wasm")))]
+    #[test]
+    fn sub_millisecond_spin_threshold_sleeps() {
+        // a one millisecond rollback is over the threshold, even though it's only 999µs
+        let (_, clock) = scripted_clock(|read| if read == 1 { 999 } else { 1_000 });
+        let generator = SnowflakeGenerator::new(0)
+            .with_clock(clock)
+            .with_spin_threshold_us(999);
+
+        generator.generate::<0>(0);
+        let start = std::time::Instant::now();
+        generator.generate::<0>(0);
+        assert!(start.elapsed() >= Duration::from_millis(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "was already issued")]
+    fn collision_detection_catches_duplicates() {
+        // with the clock stuck, the counter wrapping around produces a duplicate
+        let generator = SnowflakeGenerator::new(0)
+            .with_clock(|| FERRIS_EPOCH)
+            .with_collision_detection(1 << 17);
+        for _ in 0..=u16::MAX as u32 + 1 {
+            generator.generate::<0>(0);
+        }
+    }
+
+    #[test]
+    fn collision_detection_forgets_old_snowflakes() {
+        let generator = SnowflakeGenerator::new(0)
+            .with_clock(|| FERRIS_EPOCH)
+            .with_collision_detection(1_000);
+        for _ in 0..=u16::MAX as u32 + 1 {
+            generator.generate::<0>(0);
+        }
+    }
+
+    #[test]
+    fn counter_warning_fires_once_per_millisecond() {
+        let (now, clock) = mock_clock(1_000);
+        let warnings = Arc::new(AtomicU32::new(0));
+        let hook_warnings = Arc::clone(&warnings);
+        let generator = SnowflakeGenerator::new(0)
+            .with_clock(clock)
+            .with_counter_warn_hook(move |timestamp, count| {
+                assert_eq!(count, 101);
+                assert!(timestamp >= 1_000);
+                hook_warnings.fetch_add(1, Ordering::Relaxed);
+            });
+        generator.set_counter_warn_threshold(100);
+
+        for _ in 0..100 {
+            generator.generate::<0>(0);
+        }
+        assert_eq!(warnings.load(Ordering::Relaxed), 0);
+        for _ in 0..100 {
+            generator.generate::<0>(0);
+        }
+        assert_eq!(warnings.load(Ordering::Relaxed), 1);
+
+        now.store(1_001, Ordering::Relaxed);
+        for _ in 0..101 {
+            generator.generate::<0>(0);
+        }
+        assert_eq!(warnings.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn counter_wrap_hook_fires_once_per_wrap() {
+        let wraps = Arc::new(AtomicU32::new(0));
+        let hook_wraps = Arc::clone(&wraps);
+        let generator = SnowflakeGenerator::new(0)
+            .with_clock(|| FERRIS_EPOCH + 1_000)
+            .with_counter_wrap_hook(move || {
+                hook_wraps.fetch_add(1, Ordering::Relaxed);
+            });
+
+        for _ in 0..u16::MAX {
+            generator.generate::<0>(0);
+        }
+        assert_eq!(wraps.load(Ordering::Relaxed), 0);
+        generator.generate::<0>(0);
+        assert_eq!(wraps.load(Ordering::Relaxed), 1);
+        for _ in 0..2 << 16 {
+            generator.generate::<0>(0);
+        }
+        assert_eq!(wraps.load(Ordering::Relaxed), 3);
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    #[test]
+    fn cached_clock_follows_the_wrapped_clock() {
+        const REFRESH_INTERVAL: Duration = Duration::from_micros(250);
+        let (now, clock) = mock_clock(1_000);
+        let generator = SnowflakeGenerator::new(0)
+            .with_clock(clock)
+            .with_cached_clock(REFRESH_INTERVAL);
+        let mut seen = HashSet::new();
+        for _ in 0..10_000 {
+            let id = generator.generate::<0>(0);
+            assert!(seen.insert(id));
+            assert_eq!(timestamp_of(id), 1_000);
+        }
+
+        // the cache only notices the clock moving once the background thread refreshes it
+        now.store(1_001, Ordering::Relaxed);
+        let mut refreshed = false;
+        for _ in 0..1_000 {
+            let id = generator.generate::<0>(0);
+            assert!(seen.insert(id));
+            if timestamp_of(id) == 1_001 {
+                refreshed = true;
+                break;
+            }
+            assert_eq!(timestamp_of(id), 1_000);
+            std::thread::sleep(REFRESH_INTERVAL);
+        }
+        assert!(refreshed);
+        for _ in 0..10_000 {
+            assert_eq!(timestamp_of(generator.generate::<0>(0)), 1_001);
+        }
+    }
+
+    #[test]
+    fn random_counter_seed_survives_missing_entropy() {
+        let seeded = || {
+            SnowflakeGenerator::with_counter(1, AtomicU32::new(0))
+                .with_counter_seed_from(|| Err(io::Error::other("no entropy here")))
+        };
+        let first = AtomicU32::counter_of(seeded().generate::<0>(0));
+        let second = AtomicU32::counter_of(seeded().generate::<0>(0));
+        assert_ne!(first, second);
+
+        let generator = SnowflakeGenerator::new(1).with_counter_seed_from(|| Ok(1_234));
+        assert_eq!(counter_of(generator.generate::<0>(0)), 1_234);
+    }
+
+    #[test]
+    fn free_function_and_generator_agree() {
+        let (free, generated) = with_frozen_time(1_234, || {
+            (
+                generate_snowflake::<3>(4, 5),
+                SnowflakeGenerator::new(5).generate::<3>(4),
+            )
+        });
+        assert_eq!(timestamp_of(free), timestamp_of(generated));
+        assert_eq!(model_type_of(free), model_type_of(generated));
+        assert_eq!(api_version_of(free), api_version_of(generated));
+        assert_eq!(node_id_of(free), node_id_of(generated));
+        // the counters are separate, but every other bit is laid out the same
+        assert_eq!(without_counter(free), without_counter(generated));
+    }
+
+    #[test]
+    fn min_timestamp_floors_early_snowflakes() {
+        let (now, clock) = mock_clock(1_000);
+        let generator = SnowflakeGenerator::new(0)
+            .with_clock(clock)
+            .with_min_timestamp(FERRIS_EPOCH + 5_000);
+
+        let early: Vec<_> = (0..100).map(|_| generator.generate::<0>(0)).collect();
+        assert!(early.iter().all(|&id| timestamp_of(id) == 5_000));
+        assert_eq!(early.iter().collect::<HashSet<_>>().len(), early.len());
+
+        // once the clock passes the floor, it takes over again
+        now.store(6_000, Ordering::Relaxed);
+        let late = generator.generate::<0>(0);
+        assert_eq!(timestamp_of(late), 6_000);
+        assert!(early.iter().all(|&id| id < late));
+    }
+
+    #[test]
+    fn event_history_keeps_the_most_recent_events() {
+        let generator = SnowflakeGenerator::new(0)
+            .with_clock(|| FERRIS_EPOCH + 1_000)
+            .with_event_history(4);
+        assert!(generator.recent_events().is_empty());
+        assert!(SnowflakeGenerator::new(0).recent_events().is_empty());
+
+        let ids: Vec<_> = (0..10).map(|_| generator.generate::<0>(0)).collect();
+        let events = generator.recent_events();
+        assert_eq!(events.len(), 4);
+        for (event, id) in events.iter().zip(&ids[6..]) {
+            assert_eq!(event.timestamp, timestamp_of(*id));
+            assert_eq!(event.counter, counter_of(*id) as u32);
+            assert!(!event.rolled_back);
+            assert!(!event.wrapped);
+        }
+    }
+
+    #[cfg(feature = "time-safety-checks")]
+    #[test]
+    fn event_history_flags_rollbacks() {
+        let (now, clock) = mock_clock(1_000);
+        // a rollback within the spin threshold is waited out without sleeping
+        let generator = SnowflakeGenerator::new(0)
+            .with_clock(clock)
+            .with_event_history(4)
+            .with_spin_threshold_us(u64::MAX);
+        generator.generate::<0>(0);
+        now.store(999, Ordering::Relaxed);
+        let clock_now = Arc::clone(&now);
+        let catch_up = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            clock_now.store(1_000, Ordering::Relaxed);
+        });
+        generator.generate::<0>(0);
+        catch_up.join().unwrap();
+        let events = generator.recent_events();
+        assert_eq!(events.len(), 2);
+        assert!(!events[0].rolled_back);
+        assert!(events[1].rolled_back);
+    }
+
+    #[test]
+    fn event_history_flags_wraps() {
+        let generator = SnowflakeGenerator::with_counter(0, AtomicU8::new(0))
+            .with_clock(|| FERRIS_EPOCH + 1_000)
+            .with_event_history(2);
+        for _ in 0..u8::MAX {
+            generator.generate::<0>(0);
+        }
+        let events = generator.recent_events();
+        assert_eq!((events[0].counter, events[0].wrapped), (253, false));
+        assert_eq!((events[1].counter, events[1].wrapped), (254, false));
+        generator.generate::<0>(0);
+        let events = generator.recent_events();
+        assert_eq!((events[1].counter, events[1].wrapped), (255, true));
+    }
+
+    #[test]
+    fn wide_counter_allows_more_per_millisecond() {
+        let generator =
+            SnowflakeGenerator::with_counter(0, AtomicU32::new(0)).with_clock(|| FERRIS_EPOCH);
+        let mut seen = HashSet::with_capacity(100_000);
+        for _ in 0..100_000 {
+            let id = generator.generate::<0>(0);
+            assert_eq!(timestamp_of(id), 0);
+            assert!(seen.insert(id));
+        }
+    }
+
+    #[test]
+    fn checked_generation_survives_backward_clock() {
+        // the clock reads 1000, rolls back to 998 for a while, then jumps to 1001
+        let (_, clock) = scripted_clock(|read| match read {
+            0..=2 => 1_000,
+            3..=5 => 998,
+            _ => 1_001,
+        });
+        let generator = SnowflakeGenerator::new(0).with_clock(clock);
+        let ids: Vec<_> = (0..10)
+            .map(|_| generator.generate_checked_unique::<0>(0).unwrap())
+            .collect();
+        assert!(ids
+            .windows(2)
+            .all(|pair| chronological_cmp(pair[0], pair[1]) == CmpOrdering::Less));
+        assert_eq!(timestamp_of(ids[3]), 1_001);
+    }
+
+    #[test]
+    fn checked_generation_gives_up_on_stuck_clock() {
+        let generator = SnowflakeGenerator::new(0).with_clock(|| FERRIS_EPOCH);
+        for _ in 0..=u16::MAX {
+            generator.generate_checked_unique::<0>(0).unwrap();
+        }
+        assert_eq!(
+            generator.generate_checked_unique::<0>(0),
+            Err(SnowflakeError::NoProgress)
+        );
+    }
+
+    #[test]
+    fn strict_epoch_refuses_to_generate_early() {
+        let epoch = get_epoch_time() + FERRIS_EPOCH + HOUR_MS;
+        let generator = SnowflakeGenerator::new(0)
+            .with_epoch(epoch)
+            .with_strict_epoch();
+        match generator.try_generate::<0>(0) {
+            Err(SnowflakeError::EpochNotReached { remaining_ms }) => {
+                assert!(remaining_ms <= HOUR_MS);
+                assert!(remaining_ms > HOUR_MS - 60_000);
+            }
+            result => panic!("expected EpochNotReached, got {:?}", result),
+        }
+        assert!(matches!(
+            generator.generate_checked_unique::<0>(0),
+            Err(SnowflakeError::EpochNotReached { .. })
+        ));
+
+        // without strict mode, snowflakes before the epoch are stamped with 0
+        let lenient = SnowflakeGenerator::new(0).with_epoch(epoch);
+        assert_eq!(timestamp_of(lenient.try_generate::<0>(0).unwrap()), 0);
+    }
+
+    #[test]
+    fn strict_epoch_generates_once_reached() {
+        let generator = SnowflakeGenerator::new(0)
+            .with_epoch(FERRIS_EPOCH + 1_000)
+            .with_clock(|| FERRIS_EPOCH + 1_500)
+            .with_strict_epoch();
+        assert_eq!(timestamp_of(generator.try_generate::<0>(0).unwrap()), 500);
+        assert_eq!(timestamp_of(generator.generate::<0>(0)), 500);
+    }
+
+    /// Generates every counter value in frozen millisecond 1,000 with `policy`, then tries to
+    /// generate one more snowflake right at the boundary where the counter wraps from 65,535 to 0.
+    ///
+    /// The clock only moves on to millisecond 1,001 a few reads after the boundary is crossed, so
+    /// a policy that waits for the next millisecond gets there.
+    fn generate_across_wrap(policy: CounterPolicy) -> (Vec<u128>, Result<u128, SnowflakeError>) {
+        const BOUNDARY: u64 = 1 << 16;
+        let (_, clock) = scripted_clock(|read| if read < BOUNDARY + 10 { 1_000 } else { 1_001 });
+        let generator = SnowflakeGenerator::new(0)
+            .with_clock(clock)
+            .with_counter_policy(policy);
+        let before: Vec<_> = (0..BOUNDARY)
+            .map(|_| generator.try_generate::<0>(0).unwrap())
+            .collect();
+        assert!(before.iter().all(|&id| timestamp_of(id) == 1_000));
+        assert_eq!(counter_of(before[0]), 0);
+        assert_eq!(counter_of(*before.last().unwrap()), u16::MAX);
+        let across = generator.try_generate::<0>(0);
+        (before, across)
+    }
+
+    #[test]
+    fn wrap_policy_duplicates_at_the_boundary() {
+        let (before, across) = generate_across_wrap(CounterPolicy::Wrap);
+        // this is the documented cost of never blocking
+        assert_eq!(across, Ok(before[0]));
+    }
+
+    #[test]
+    fn spin_policy_advances_past_the_boundary() {
+        let (before, across) = generate_across_wrap(CounterPolicy::SpinToNextMs);
+        let across = across.unwrap();
+        assert_eq!(timestamp_of(across), 1_001);
+        assert_eq!(counter_of(across), 0);
+        assert!(before.iter().all(|&id| id < across));
+    }
+
+    #[test]
+    fn error_policy_errors_at_the_boundary() {
+        let (_, across) = generate_across_wrap(CounterPolicy::Error);
+        assert_eq!(across, Err(SnowflakeError::CounterExhausted));
+    }
+
+    #[test]
+    fn saturating_generation_stops_at_the_limit() {
+        let (now, clock) = mock_clock(1_000);
+        let generator = SnowflakeGenerator::new(0).with_clock(clock);
+        let ids: HashSet<_> = (0..1 << 16)
+            .map(|_| generator.saturating_generate::<0>(0).unwrap())
+            .collect();
+        assert_eq!(ids.len(), 1 << 16);
+        assert_eq!(generator.saturating_generate::<0>(0), None);
+        assert_eq!(generator.saturating_generate::<0>(0), None);
+
+        now.store(1_001, Ordering::Relaxed);
+        let id = generator.saturating_generate::<0>(0).unwrap();
+        assert_eq!(timestamp_of(id), 1_001);
+        assert!(!ids.contains(&id));
+    }
+
+    #[test]
+    fn barrier_separates_timestamps() {
+        let (now, clock) = mock_clock(1_000);
+        let generator = SnowflakeGenerator::new(0).with_clock(clock);
+        let before: Vec<_> = (0..100).map(|_| generator.generate::<0>(0)).collect();
+        assert!(before.iter().all(|&id| timestamp_of(id) == 1_000));
+
+        // the clock is frozen, so the barrier has to move the timestamps on by itself
+        generator.barrier();
+        let after: Vec<_> = (0..100).map(|_| generator.generate::<0>(0)).collect();
+        assert!(after.iter().all(|&id| timestamp_of(id) == 1_001));
+        generator.barrier();
+        assert_eq!(timestamp_of(generator.generate::<0>(0)), 1_002);
+
+        // once the clock passes the barrier, it takes over again
+        now.store(1_005, Ordering::Relaxed);
+        assert_eq!(timestamp_of(generator.generate::<0>(0)), 1_005);
+    }
+
+    #[test]
+    fn barrier_doesnt_wait_for_a_future_floor() {
+        let (_, clock) = mock_clock(1_000);
+        let generator = SnowflakeGenerator::new(0)
+            .with_clock(clock)
+            .with_min_timestamp(FERRIS_EPOCH + 1_000_000);
+        assert_eq!(timestamp_of(generator.generate::<0>(0)), 1_000_000);
+        generator.barrier();
+        assert_eq!(timestamp_of(generator.generate::<0>(0)), 1_000_001);
+    }
+
+    #[cfg(not(feature = "time-safety-checks"))]
+    #[test]
+    fn barrier_holds_across_a_rollback() {
+        let (now, clock) = mock_clock(1_000);
+        let generator = SnowflakeGenerator::new(0).with_clock(clock);
+        let before = generator.generate::<0>(0);
+        now.store(900, Ordering::Relaxed);
+        generator.barrier();
+        let after = generator.generate::<0>(0);
+        assert_eq!(timestamp_of(after), 1_001);
+        assert!(after > before);
+    }
+
+    #[test]
+    fn adaptive_spin_makes_progress_under_saturation() {
+        // the clock only advances once every 100,000 reads, so every millisecond saturates
+        let (_, clock) = scripted_clock(|read| read / 100_000);
+        let generator = SnowflakeGenerator::new(1)
+            .with_clock(clock)
+            .with_counter_policy(CounterPolicy::AdaptiveSpin);
+
+        let mut last = 0;
+        let mut seen = HashSet::new();
+        for _ in 0..5 * (1 << 16) {
+            let id = generator.generate::<0>(0);
+            assert!(seen.insert(id));
+            assert!(id > last);
+            last = id;
+        }
+        assert!(timestamp_of(last) >= 4);
+        assert!(generator.exhausted_streak.load(Ordering::Relaxed) >= ADAPTIVE_YIELD_STREAK);
+    }
+
+    #[test]
+    fn shared_generator_is_unique_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SnowflakeGenerator>();
+        assert_send_sync::<SnowflakeGenerator<AtomicU32>>();
+
+        let generator = Arc::new(SnowflakeGenerator::new(0));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let generator = Arc::clone(&generator);
+                std::thread::spawn(move || {
+                    (0..5_000)
+                        .map(|_| generator.generate::<0>(0))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let mut seen = HashSet::new();
+        for handle in handles {
+            for id in handle.join().unwrap() {
+                assert!(seen.insert(id));
+            }
+        }
+        assert_eq!(seen.len(), 8 * 5_000);
+    }
+
+    #[test]
+    fn node_id_swaps_mid_generation() {
+        let generator = Arc::new(SnowflakeGenerator::new(1));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let generator = Arc::clone(&generator);
+                std::thread::spawn(move || {
+                    (0..10_000)
+                        .map(|_| generator.generate::<0>(0))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        generator.set_node_id(2);
+        assert_eq!(generator.node_id(), 2);
+
+        let mut seen = HashSet::new();
+        for handle in handles {
+            for id in handle.join().unwrap() {
+                assert!(matches!(node_id_of(id), 1 | 2));
+                assert!(seen.insert(id));
+            }
+        }
+        assert_eq!(node_id_of(generator.generate::<0>(0)), 2);
+    }
+
+    #[test]
+    fn provider_releases_the_acquired_node_id() {
+        let provider = Arc::new(crate::InMemoryNodeIdProvider::new());
+        let generator = SnowflakeGenerator::with_provider(Arc::clone(&provider)).unwrap();
+        generator.set_node_id(1_000);
+        drop(generator);
+        assert_eq!(provider.acquire(), Ok(0));
+    }
+
+    #[test]
+    fn pid_component_separates_processes() {
+        let generator_1 = SnowflakeGenerator::new(3)
+            .with_clock(|| FERRIS_EPOCH)
+            .with_pid_component_from(4, 0x1234);
+        let generator_2 = SnowflakeGenerator::new(3)
+            .with_clock(|| FERRIS_EPOCH)
+            .with_pid_component_from(4, 0x1235);
+        let ids_1: HashSet<_> = (0..1_000).map(|_| generator_1.generate::<0>(0)).collect();
+        let ids_2: HashSet<_> = (0..1_000).map(|_| generator_2.generate::<0>(0)).collect();
+        assert!(ids_1.is_disjoint(&ids_2));
+
+        // 0x1234 folds to 1 ^ 2 ^ 3 ^ 4 = 4, and 0x1235 to 5
+        let id = ids_1.into_iter().next().unwrap();
+        assert_eq!(node_id_of(id), 3 << 4 | 4);
+        assert_eq!(node_id_of(generator_2.generate::<0>(0)), 3 << 4 | 5);
+        assert_eq!(generator_1.node_id(), 3);
+    }
+
+    #[test]
+    fn pid_folding() {
+        assert_eq!(fold(0xABCD, 0), 0);
+        assert_eq!(fold(0x1234, 4), 4);
+        assert_eq!(fold(0x0001_0002, 16), 3);
+        assert_eq!(fold(u32::MAX, 32), u32::MAX);
+        assert_eq!(
+            SnowflakeGenerator::new(0)
+                .with_pid_component_from(16, 0x0001_0002)
+                .node_field(),
+            3
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "does not fit")]
+    fn pid_component_rejects_wide_node_ids() {
+        let _ = SnowflakeGenerator::new(1 << 12).with_pid_component_from(4, 0);
+    }
+}
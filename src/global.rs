@@ -0,0 +1,76 @@
+use crate::{SnowflakeError, SnowflakeGenerator};
+use std::sync::OnceLock;
+
+/// The process-wide generator behind [`global`].
+static GLOBAL: OnceLock<SnowflakeGenerator> = OnceLock::new();
+
+/// Configures the process-wide generator returned by [`global`] with a node ID.
+///
+/// This must be called once at startup, before anything uses the global generator: the first use
+/// creates it with [`SnowflakeGenerator::default`] (node 0) if it hasn't been configured yet,
+/// after which it can't be configured anymore.
+///
+/// # Errors
+/// Returns [`SnowflakeError::GlobalAlreadyInitialized`] if the global generator was already
+/// configured or used.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{global, init_global, node_id_of};
+/// init_global(42).unwrap();
+/// assert_eq!(node_id_of(global().generate::<0>(0)), 42);
+/// assert!(init_global(43).is_err());
+/// ```
+pub fn init_global(node_id: u16) -> Result<(), SnowflakeError> {
+    init(&GLOBAL, node_id)
+}
+
+/// Returns the process-wide generator, configured with [`init_global`].
+///
+/// If [`init_global`] hasn't been called yet, this creates the generator with
+/// [`SnowflakeGenerator::default`], stamping node 0 onto every snowflake.
+#[must_use]
+pub fn global() -> &'static SnowflakeGenerator {
+    GLOBAL.get_or_init(SnowflakeGenerator::default)
+}
+
+/// Configures `global` with a node ID, unless it's already set.
+fn init(global: &OnceLock<SnowflakeGenerator>, node_id: u16) -> Result<(), SnowflakeError> {
+    global
+        .set(SnowflakeGenerator::new(node_id))
+        .map_err(|_| SnowflakeError::GlobalAlreadyInitialized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node_id_of;
+
+    #[test]
+    fn initialized_global_uses_its_node() {
+        let global = OnceLock::new();
+        init(&global, 42).unwrap();
+        let generator = global.get().unwrap();
+        assert_eq!(generator.node_id(), 42);
+        assert_eq!(node_id_of(generator.generate::<0>(0)), 42);
+    }
+
+    #[test]
+    fn double_init_errors() {
+        let global = OnceLock::new();
+        init(&global, 42).unwrap();
+        assert_eq!(
+            init(&global, 43),
+            Err(SnowflakeError::GlobalAlreadyInitialized)
+        );
+        assert_eq!(global.get().unwrap().node_id(), 42);
+
+        // using the global before configuring it also locks in the default
+        let global = OnceLock::new();
+        global.get_or_init(SnowflakeGenerator::default);
+        assert_eq!(
+            init(&global, 43),
+            Err(SnowflakeError::GlobalAlreadyInitialized)
+        );
+    }
+}
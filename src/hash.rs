@@ -0,0 +1,92 @@
+/// The FNV-1a 64-bit offset basis.
+const FNV_OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+/// The FNV-1a 64-bit prime.
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+/// Maps a snowflake to one of `shards` shards, the same way in every process and every language.
+///
+/// The algorithm is fixed, so other implementations can reproduce it exactly:
+/// 1. Take the 16 bytes of the snowflake in big-endian order.
+/// 2. Hash them with 64-bit FNV-1a: start from `0xCBF29CE484222325`, and for each byte, XOR it
+///    into the hash and then multiply the hash by `0x100000001B3`, wrapping on overflow.
+/// 3. Fold the high 32 bits of the hash into the low 32 bits by XORing it with itself shifted
+///    right by 32, since FNV-1a's low bits only depend on the input's low bits.
+/// 4. Return the result modulo `shards`.
+///
+/// # Panics
+/// Panics if `shards` is 0.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::shard_hash;
+/// assert_eq!(shard_hash(0, 1_000), 452);
+/// assert_eq!(shard_hash(1 << 64, 7), 5);
+/// ```
+#[inline]
+pub const fn shard_hash(id: u128, shards: u32) -> u32 {
+    assert!(shards != 0, "can't hash into 0 shards");
+    let bytes = id.to_be_bytes();
+    let mut hash = FNV_OFFSET_BASIS;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+        i += 1;
+    }
+    hash ^= hash >> 32;
+    (hash % shards as u64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pack_with_counter, SnowflakeGenerator};
+
+    #[test]
+    fn shard_hash_is_stable() {
+        // the folded 64-bit FNV-1a hashes of these, as computed by a reference implementation
+        assert_eq!(
+            shard_hash(0, u32::MAX),
+            (0x8820_1FB9_E8DF_7BDC % u32::MAX as u64) as u32
+        );
+        assert_eq!(
+            shard_hash(1 << 64, u32::MAX),
+            (0xF4B5_CA5B_38D1_A409 % u32::MAX as u64) as u32
+        );
+        let id = SnowflakeGenerator::new(0).generate::<0>(0);
+        assert_eq!(shard_hash(id, 16), shard_hash(id, 16));
+        assert_eq!(shard_hash(id, 1), 0);
+    }
+
+    #[test]
+    fn shard_hash_is_roughly_uniform() {
+        const IDS: u32 = 64_000;
+        fn check(shards: u32, id: impl Fn(u32) -> u128) {
+            let mut counts = vec![0u32; shards as usize];
+            for i in 0..IDS {
+                counts[shard_hash(id(i), shards) as usize] += 1;
+            }
+            // every shard should get within 10% of its fair share
+            let fair = IDS / shards;
+            for &count in &counts {
+                assert!(count.abs_diff(fair) < fair / 10, "{:?} is lopsided", counts);
+            }
+        }
+        for shards in [7, 10, 16] {
+            // a burst within one millisecond, a steady trickle, and one per millisecond
+            check(shards, |i| pack_with_counter::<0>(1_000, 0, i as u16, 0));
+            check(shards, |i| {
+                pack_with_counter::<0>(1_000 + i as u128, 0, i as u16, 0)
+            });
+            check(shards, |i| {
+                pack_with_counter::<0>(1_000 + i as u128, 0, 0, 0)
+            });
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "can't hash into 0 shards")]
+    fn zero_shards_panics() {
+        let _ = shard_hash(0, 0);
+    }
+}
@@ -0,0 +1,183 @@
+use std::sync::atomic::{self, AtomicU64, Ordering};
+
+/// A record of one snowflake being generated, kept by a generator with
+/// [`with_event_history`](crate::SnowflakeGenerator::with_event_history).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnowflakeEvent {
+    /// The timestamp the snowflake was stamped with, in milliseconds since the generator's epoch.
+    pub timestamp: u128,
+    /// The counter the snowflake was stamped with.
+    pub counter: u32,
+    /// Whether the clock was caught rolling back while reading the timestamp.
+    pub rolled_back: bool,
+    /// Whether the counter wrapped back around to 0 after this snowflake.
+    pub wrapped: bool,
+}
+
+/// The bit of a slot's `meta` that holds [`SnowflakeEvent::rolled_back`].
+const ROLLED_BACK: u64 = 1 << 32;
+/// The bit of a slot's `meta` that holds [`SnowflakeEvent::wrapped`].
+const WRAPPED: u64 = 1 << 33;
+
+/// One remembered event, guarded by a sequence number so it can be written and read without a
+/// lock.
+///
+/// The event numbered `n` is being written while `seq` is `2n + 1`, and has been written once
+/// `seq` is `2n + 2`. A `seq` of 0 means the slot has never been written.
+#[derive(Default)]
+struct Slot {
+    seq: AtomicU64,
+    timestamp: AtomicU64,
+    /// The counter in the low 32 bits, then the [`ROLLED_BACK`] and [`WRAPPED`] flags.
+    meta: AtomicU64,
+}
+
+/// Remembers the most recent generation events for post-mortem inspection.
+///
+/// The events live in a fixed ring of atomic slots, so recording one never takes a lock or waits:
+/// each event takes the next slot in turn, overwriting the event from one lap ago. The price is
+/// that an event is dropped if its slot is still being written by a thread that stalled for a
+/// whole lap of the ring.
+pub(crate) struct EventHistory {
+    slots: Box<[Slot]>,
+    /// How many events have ever been recorded, which is also the number of the next one.
+    recorded: AtomicU64,
+}
+
+impl EventHistory {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            slots: (0..capacity).map(|_| Slot::default()).collect(),
+            recorded: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the slot the event numbered `n` goes in.
+    #[inline]
+    fn slot(&self, n: u64) -> &Slot {
+        &self.slots[(n % self.slots.len() as u64) as usize]
+    }
+
+    /// Remembers `event`, forgetting the oldest event if full.
+    #[inline]
+    pub(crate) fn record(&self, event: SnowflakeEvent) {
+        if self.slots.is_empty() {
+            return;
+        }
+        let n = self.recorded.fetch_add(1, Ordering::Relaxed);
+        let slot = self.slot(n);
+        let mut seq = slot.seq.load(Ordering::Relaxed);
+        loop {
+            // a later event having taken the slot over means this one is forgotten already, and
+            // an event from a lap ago still being written means that thread stalled: rather than
+            // wait for it, forget this event too
+            if seq > 2 * n || seq % 2 == 1 {
+                return;
+            }
+            match slot.seq.compare_exchange_weak(
+                seq,
+                2 * n + 1,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(current) => seq = current,
+            }
+        }
+        // keep the event's fields from being written before the slot is marked as being written
+        atomic::fence(Ordering::Release);
+        slot.timestamp
+            .store(event.timestamp as u64, Ordering::Relaxed);
+        let flags = if event.rolled_back { ROLLED_BACK } else { 0 }
+            | if event.wrapped { WRAPPED } else { 0 };
+        slot.meta
+            .store(u64::from(event.counter) | flags, Ordering::Relaxed);
+        slot.seq.store(2 * n + 2, Ordering::Release);
+    }
+
+    /// Returns the remembered events, oldest first.
+    ///
+    /// Events that are still being written, or that get overwritten while being read, are left
+    /// out.
+    pub(crate) fn events(&self) -> Vec<SnowflakeEvent> {
+        let recorded = self.recorded.load(Ordering::Acquire);
+        let first = recorded.saturating_sub(self.slots.len() as u64);
+        (first..recorded)
+            .filter_map(|n| {
+                let slot = self.slot(n);
+                let seq = slot.seq.load(Ordering::Acquire);
+                if seq != 2 * n + 2 {
+                    return None;
+                }
+                let timestamp = slot.timestamp.load(Ordering::Relaxed);
+                let meta = slot.meta.load(Ordering::Relaxed);
+                // keep the fields from being read after the sequence number is checked again
+                atomic::fence(Ordering::Acquire);
+                (slot.seq.load(Ordering::Relaxed) == seq).then_some(SnowflakeEvent {
+                    timestamp: timestamp as u128,
+                    counter: meta as u32,
+                    rolled_back: meta & ROLLED_BACK != 0,
+                    wrapped: meta & WRAPPED != 0,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn event(counter: u32) -> SnowflakeEvent {
+        SnowflakeEvent {
+            timestamp: 1_000 + counter as u128,
+            counter,
+            rolled_back: counter.is_multiple_of(2),
+            wrapped: counter.is_multiple_of(3),
+        }
+    }
+
+    #[test]
+    fn ring_keeps_the_last_capacity_events() {
+        let history = EventHistory::new(3);
+        assert!(history.events().is_empty());
+        history.record(event(0));
+        assert_eq!(history.events(), [event(0)]);
+        for counter in 1..10 {
+            history.record(event(counter));
+        }
+        assert_eq!(history.events(), [event(7), event(8), event(9)]);
+
+        let empty = EventHistory::new(0);
+        empty.record(event(0));
+        assert!(empty.events().is_empty());
+    }
+
+    #[test]
+    fn concurrent_events_are_never_torn() {
+        let history = Arc::new(EventHistory::new(4));
+        let writers: Vec<_> = (0..4)
+            .map(|_| {
+                let history = Arc::clone(&history);
+                std::thread::spawn(move || {
+                    for counter in 0..10_000 {
+                        history.record(event(counter));
+                    }
+                })
+            })
+            .collect();
+        while !writers.iter().all(|writer| writer.is_finished()) {
+            for read in history.events() {
+                assert_eq!(read, event(read.counter));
+            }
+        }
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        // an event is only dropped if its slot was still being written a lap later
+        let events = history.events();
+        assert!(!events.is_empty() && events.len() <= 4);
+        assert!(events.iter().all(|&read| read == event(read.counter)));
+    }
+}
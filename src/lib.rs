@@ -9,31 +9,151 @@
 //!
 //! # Format
 //! * Bits 0 to 63: milliseconds since the Ferris Epoch (01/01/2022 00:00:00.0000+00:00).
-//! Range of around 600,000,000 years.
+//!   Range of around 600,000,000 years.
 //! * Bits 64 to 71: the type of model (i.e. user, channel, guild)
-//! * Bits 73 to 85: internal 16-bit atomic counter
-//! * Bits 86 to 93: the API version this ID was generated with
-//! * Bits 94 to 109: the node this ID was generated on
-//! * Bits 110 to 127: unused
+//! * Bits 72 to 87: internal 16-bit atomic counter
+//! * Bits 88 to 95: the API version this ID was generated with
+//! * Bits 96 to 111: the node this ID was generated on
+//! * Bit 112: whether this ID is for a system-generated entity
+//! * Bits 113 to 116: the shard this ID routes to, if generated with `generate_sharded`
+//! * Bits 117 to 118: the version of the format this ID is laid out in, currently always 0
+//! * Bits 119 to 127: unused
+//!
+//! # Platform Support
+//! Any target with 64-bit atomics, which the timestamp bookkeeping relies on. Targets without them
+//! (such as some 32-bit embedded ones) fail to compile with an explicit error.
+//!
+//! # Migrating from 2.x
+//! Version 3.0.0 changes the bit layout. In 2.x the counter occupied bits 70 to 85, overlapping
+//! the low two bits of the model type, and the API version and node sat two bits earlier, at bits
+//! 86 to 93 and 94 to 109. Snowflakes generated by 2.x keep their timestamps, but decoding them
+//! with the functions for the current layout returns the wrong counter, API version and node:
+//! use [`decode_legacy`] for those instead. Both layouts leave the format version bits unset, so
+//! the two can't be told apart from the snowflake alone.
+//!
+//! The 18 bits below the node (110 to 127) were unused in 2.x. In 3.0.0 the node takes the first
+//! two of them, and the other 16 hold the system flag, shard, format version and 9 reserved bits,
+//! so any data an application packed into them by hand reads back as those fields.
 //!
 //! # Crate Features
 //! * `time-safety-checks`: checks that the system clock has not rolled back since the last
-//! snowflake generated and if it has, blocks until the time is after the time of the last snowflake.
-//! Adds a slight performance penalty but isn't that noticeable. Enabled by default.
+//!   snowflake generated and if it has, blocks until the time is after the time of the last snowflake.
+//!   Adds a slight performance penalty but isn't that noticeable. Enabled by default.
+//! * `frozen-time`: adds `with_frozen_time` for freezing the system clock in tests.
+//!   Not for production use.
+//! * `wasm`: adds `set_time_source` for reading the time from the host instead of `SystemTime`,
+//!   and waits out clock rollbacks by spinning instead of sleeping, for targets such as
+//!   `wasm32-unknown-unknown` where neither is available.
+//! * `ffi`: adds `Snowflake::as_u128_slice` for reinterpreting slices of snowflakes without
+//!   copying. This is the only feature that needs unsafe code: without it, the crate forbids
+//!   unsafe code entirely.
+
+#![cfg_attr(not(feature = "ffi"), forbid(unsafe_code))]
+// the `ffi` feature allows unsafe code only where it's explicitly allowed and justified
+#![cfg_attr(feature = "ffi", deny(unsafe_code))]
+
+// the last-timestamp bookkeeping is in 64-bit atomics, since 128-bit ones are rarer still
+#[cfg(not(target_has_atomic = "64"))]
+compile_error!("this crate needs a target with 64-bit atomics");
+
+mod age;
+mod capacity;
+mod clock;
+mod cluster;
+mod collision;
+mod counter;
+mod decode;
+mod encoding;
+mod epoch;
+mod error;
+mod generator;
+mod global;
+mod hash;
+mod history;
+mod parts;
+mod policy;
+mod provider;
+mod range;
+mod scope;
+mod seed;
+mod snowflake;
+mod snowflake64;
+mod window;
+
+pub use age::{age_of, relative_time_of};
+pub use capacity::{collision_probability, ms_until_exhaustion};
+#[cfg(feature = "wasm")]
+pub use clock::set_time_source;
+#[cfg(any(test, feature = "frozen-time"))]
+pub use clock::with_frozen_time;
+pub use clock::{Clock, SystemClock};
+pub use counter::Counter;
+pub use decode::{
+    api_version_of, chronological_cmp, counter_distance, counter_of, format_version_of, group_key,
+    identity_bits, is_system, looks_like_snowflake, model_type_of, node_id_of, reserved_of,
+    same_instant, set_reserved, shard_of, supports_api_version, time_bucket, time_diff_ms,
+    timestamp_of, unix_timestamp_ms_of, verify_monotonic,
+};
+pub use encoding::{
+    decode_base62, decode_base62_with_checksum, encode_base62, encode_base62_with_checksum,
+    from_sortable_key, from_varint, to_sortable_key, to_varint,
+};
+pub use epoch::{rebase_epoch, rebase_epoch_many};
+pub use error::{DecodeError, MonotonicityViolation, SnowflakeError};
+pub use generator::SnowflakeGenerator;
+pub use global::{global, init_global};
+pub use hash::shard_hash;
+pub use history::SnowflakeEvent;
+pub use parts::{decode_legacy, decode_snowflake, SnowflakeParts, SnowflakePartsBuilder};
+pub use policy::CounterPolicy;
+pub use provider::{InMemoryNodeIdProvider, NodeIdProvider};
+pub use range::{cursor_after, cursor_before, day_range, max_for_timestamp, min_for_timestamp};
+pub use scope::RequestScope;
+pub use snowflake::{NewestFirst, Snowflake};
+pub use snowflake64::{generate_snowflake64, Snowflake64};
 
-use std::sync::atomic::{AtomicU16, Ordering};
 #[cfg(feature = "time-safety-checks")]
-use std::time::Duration;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::{AtomicU16, Ordering};
 
 /// A internal atomic counter that helps guarantee snowflakes will be globally unique.
 static INTERNAL_COUNTER: AtomicU16 = AtomicU16::new(0);
 /// The start of the Ferris Epoch in milliseconds since the Unix Epoch
 pub const FERRIS_EPOCH: u128 = 1_640_995_200_000;
+/// Whether this crate was compiled with the `time-safety-checks` feature, which protects
+/// snowflake generation against the clock rolling back.
+pub const TIME_SAFETY_CHECKS_ENABLED: bool = cfg!(feature = "time-safety-checks");
+
+/// How far the timestamp field is shifted left within a snowflake.
+pub(crate) const TIMESTAMP_SHIFT: u32 = 64;
+/// How far the model type field is shifted left within a snowflake.
+pub(crate) const MODEL_TYPE_SHIFT: u32 = 56;
+/// How far the counter field is shifted left within a snowflake.
+pub(crate) const COUNTER_SHIFT: u32 = 40;
+/// How far the API version field is shifted left within a snowflake.
+pub(crate) const API_VERSION_SHIFT: u32 = 32;
+/// How far the node ID field is shifted left within a snowflake.
+pub(crate) const NODE_ID_SHIFT: u32 = 16;
+/// The largest timestamp that fits in a snowflake, in milliseconds since the Ferris Epoch.
+pub(crate) const MAX_TIMESTAMP: u128 = u64::MAX as u128;
+/// The bit of a snowflake that flags it as belonging to a system-generated entity.
+pub(crate) const SYSTEM_FLAG: u128 = 1 << (NODE_ID_SHIFT - 1);
+/// How far the shard field is shifted left within a snowflake.
+pub(crate) const SHARD_SHIFT: u32 = NODE_ID_SHIFT - 5;
+/// The largest shard that fits in a snowflake.
+pub(crate) const MAX_SHARD: u8 = 15;
+/// How far the format version field is shifted left within a snowflake.
+pub(crate) const FORMAT_VERSION_SHIFT: u32 = SHARD_SHIFT - 2;
+/// The largest format version that fits in a snowflake.
+pub(crate) const MAX_FORMAT_VERSION: u8 = 3;
+/// The bits of a snowflake that are reserved for future use.
+pub(crate) const RESERVED_MASK: u128 = (1 << FORMAT_VERSION_SHIFT) - 1;
 
 #[cfg(feature = "time-safety-checks")]
 /// A static variable to store the timestamp of the last snowflake generated.
-static mut LAST_TIME_CREATED: u128 = 0;
+///
+/// Atomic u128s are not available on most platforms, but a u64 of milliseconds lasts long enough.
+static LAST_TIME_CREATED: AtomicU64 = AtomicU64::new(0);
 
 /// Generates a snowflake from the current API version, the model type, and the node ID.
 ///
@@ -47,6 +167,149 @@ static mut LAST_TIME_CREATED: u128 = 0;
 /// ```
 #[inline]
 pub fn generate_snowflake<const API_VERSION: u8>(model_type: u8, node_id: u16) -> u128 {
+    generate_snowflake_dyn(API_VERSION, model_type, node_id)
+}
+
+/// Generates a snowflake like [`generate_snowflake`], with the API version known only at
+/// runtime.
+///
+/// The API version field is 8 bits wide, so every `u8` fits in it.
+///
+/// # Panics
+/// Panics if the current time is behind the Unix Epoch.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{api_version_of, generate_snowflake_dyn};
+/// assert_eq!(api_version_of(generate_snowflake_dyn(7, 0, 0)), 7);
+/// ```
+#[inline]
+pub fn generate_snowflake_dyn(api_version: u8, model_type: u8, node_id: u16) -> u128 {
+    // fetch_add wraps on overflow: this is what we want
+    let id = pack_fields(
+        current_time(),
+        model_type,
+        INTERNAL_COUNTER.fetch_add(1, Ordering::Relaxed),
+        api_version,
+        node_id,
+    );
+    // everything below the node ID stays unused until a caller ORs in a flag or shard
+    debug_assert_unused_clear(id, (1 << NODE_ID_SHIFT) - 1);
+    id
+}
+
+/// Panics in debug builds if `id` has any of the bits in `unused` set, which means it was packed
+/// wrong.
+#[inline]
+#[track_caller]
+pub(crate) fn debug_assert_unused_clear(id: u128, unused: u128) {
+    debug_assert!(
+        id & unused == 0,
+        "generated snowflake {:#034x} has unused bits {:#x} set, it was packed wrong",
+        id,
+        id & unused
+    );
+}
+
+/// Clears the counter bits of `id`, for comparing snowflakes from different counters.
+#[cfg(test)]
+pub(crate) const fn without_counter(id: u128) -> u128 {
+    id & !((u16::MAX as u128) << COUNTER_SHIFT)
+}
+
+/// Generates a snowflake like [`generate_snowflake`], additionally flagging whether it's for a
+/// system-generated entity rather than a user-generated one.
+///
+/// With `system` set to `false`, this generates exactly what [`generate_snowflake`] would.
+///
+/// # Panics
+/// Panics if the current time is behind the Unix Epoch.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{generate_system_flagged, is_system};
+/// assert!(is_system(generate_system_flagged::<0>(0, 0, true)));
+/// assert!(!is_system(generate_system_flagged::<0>(0, 0, false)));
+/// ```
+#[inline]
+pub fn generate_system_flagged<const API_VERSION: u8>(
+    model_type: u8,
+    node_id: u16,
+    system: bool,
+) -> u128 {
+    let id = generate_snowflake::<API_VERSION>(model_type, node_id);
+    if system {
+        id | SYSTEM_FLAG
+    } else {
+        id
+    }
+}
+
+/// Generates a snowflake like [`generate_snowflake`], with the counter field set to `seq`
+/// verbatim instead of taken from the internal counter.
+///
+/// This is for single-writer setups that already keep a monotonic sequence (such as a Raft log
+/// index) and want snowflakes ordered by it exactly. The caller owns uniqueness: two snowflakes
+/// generated with the same `seq` in the same millisecond are identical.
+///
+/// # Panics
+/// Panics if the current time is behind the Unix Epoch.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{counter_of, generate_with_sequence};
+/// assert_eq!(counter_of(generate_with_sequence::<0>(0, 0, 42)), 42);
+/// ```
+#[inline]
+pub fn generate_with_sequence<const API_VERSION: u8>(
+    model_type: u8,
+    node_id: u16,
+    seq: u16,
+) -> u128 {
+    let id = pack_fields(current_time(), model_type, seq, API_VERSION, node_id);
+    debug_assert_unused_clear(id, (1 << NODE_ID_SHIFT) - 1);
+    id
+}
+
+/// Generates a snowflake like [`generate_snowflake`], additionally embedding the `shard` it
+/// should be stored in, so that readers can route it with [`shard_of`] instead of looking the
+/// shard up.
+///
+/// Snowflakes generated any other way are in shard 0.
+///
+/// # Errors
+/// Returns [`SnowflakeError::ShardOverflow`] if `shard` is more than 15.
+///
+/// # Panics
+/// Panics if the current time is behind the Unix Epoch.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{generate_sharded, shard_of};
+/// assert_eq!(shard_of(generate_sharded::<0>(0, 0, 7).unwrap()), 7);
+/// assert!(generate_sharded::<0>(0, 0, 16).is_err());
+/// ```
+#[inline]
+pub fn generate_sharded<const API_VERSION: u8>(
+    model_type: u8,
+    node_id: u16,
+    shard: u8,
+) -> Result<u128, SnowflakeError> {
+    if shard > MAX_SHARD {
+        return Err(SnowflakeError::ShardOverflow);
+    }
+    Ok(generate_snowflake::<API_VERSION>(model_type, node_id) | (shard as u128) << SHARD_SHIFT)
+}
+
+/// Returns the current Ferris Epoch time to stamp a new snowflake with, blocking until the clock
+/// has caught up if it rolled back since the last snowflake (when `time-safety-checks` is on).
+#[inline]
+fn current_time() -> u128 {
+    #[cfg(any(test, feature = "frozen-time"))]
+    if let Some(frozen) = clock::frozen_time() {
+        return frozen;
+    }
+
     #[cfg(feature = "time-safety-checks")]
     let mut current_time = get_epoch_time();
     #[cfg(not(feature = "time-safety-checks"))]
@@ -54,29 +317,62 @@ pub fn generate_snowflake<const API_VERSION: u8>(model_type: u8, node_id: u16) -
 
     #[cfg(feature = "time-safety-checks")]
     {
-        // SAFETY: this is a variable we honestly do not care much about: if it's raced, we don't
-        // have a issue whatsoever with that as long as the timestamp is not stored too late
-        // which should not be possible because we update the timestamp after sleeping
-        // to add to that, atomic u128s are not available on some platforms
-        if current_time < unsafe { LAST_TIME_CREATED } {
-            let sleep_for = unsafe { LAST_TIME_CREATED + 1 } - current_time;
+        // if this is raced, we don't have a issue whatsoever with that as long as the timestamp
+        // is not stored too late, which should not be possible because we store the timestamp
+        // after sleeping, and fetch_max never moves it backwards
+        let last_time_created = LAST_TIME_CREATED.load(Ordering::Relaxed) as u128;
+        if current_time < last_time_created {
+            let sleep_for = last_time_created + 1 - current_time;
             eprintln!(
                 "detected system clock rolling back, not generating snowflakes for {}ms",
                 sleep_for
             );
-            std::thread::sleep(Duration::from_millis(sleep_for as u64));
-        }
-        current_time = get_epoch_time();
-        unsafe {
-            LAST_TIME_CREATED = current_time;
+            clock::pause(sleep_for, get_epoch_time);
+            current_time = get_epoch_time();
         }
+        LAST_TIME_CREATED.fetch_max(current_time as u64, Ordering::Relaxed);
     }
-    (current_time << 64)
-        + ((model_type as u128) << 56)
-        // fetch_add wraps on overflow: this is what we want
-        + ((INTERNAL_COUNTER.fetch_add(1, Ordering::Relaxed) as u128) << 42)
-        + ((API_VERSION as u128) << 34)
-        + ((node_id as u128) << 18)
+    current_time
+}
+
+/// Packs a snowflake from exactly the fields given, without touching the clock or the internal
+/// counter.
+///
+/// This is the primitive every other generator in this crate builds on. Because nothing is
+/// generated, the caller is responsible for making sure the result is unique, which makes it
+/// useful for deterministically replaying or reconstructing IDs.
+///
+/// Only the low 64 bits of `timestamp_ms` fit in the ID: any higher bits are discarded.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::pack_with_counter;
+/// assert_eq!(pack_with_counter::<0>(1, 0, 0, 0), 1 << 64);
+/// ```
+#[inline]
+pub const fn pack_with_counter<const API_VERSION: u8>(
+    timestamp_ms: u128,
+    model_type: u8,
+    counter: u16,
+    node_id: u16,
+) -> u128 {
+    pack_fields(timestamp_ms, model_type, counter, API_VERSION, node_id)
+}
+
+/// Packs a snowflake from exactly the fields given, with the API version known only at runtime.
+#[inline]
+pub(crate) const fn pack_fields(
+    timestamp_ms: u128,
+    model_type: u8,
+    counter: u16,
+    api_version: u8,
+    node_id: u16,
+) -> u128 {
+    (timestamp_ms << TIMESTAMP_SHIFT)
+        | ((model_type as u128) << MODEL_TYPE_SHIFT)
+        | ((counter as u128) << COUNTER_SHIFT)
+        | ((api_version as u128) << API_VERSION_SHIFT)
+        | ((node_id as u128) << NODE_ID_SHIFT)
 }
 
 /// Returns the current Ferris Epoch time.
@@ -87,11 +383,7 @@ pub fn generate_snowflake<const API_VERSION: u8>(model_type: u8, node_id: u16) -
 /// Panics if the current time is behind the Unix Epoch.
 #[inline]
 pub fn get_epoch_time() -> u128 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("you're behind the Unix Epoch")
-        .as_millis()
-        .saturating_sub(FERRIS_EPOCH)
+    SystemClock.now_ms().saturating_sub(FERRIS_EPOCH)
 }
 
 #[cfg(test)]
@@ -108,6 +400,97 @@ mod tests {
         assert_ne!(snowflake_1, snowflake_2);
     }
 
+    #[test]
+    fn max_node_and_api_version_do_not_overlap() {
+        let snowflake = generate_snowflake::<{ u8::MAX }>(0, u16::MAX);
+        assert_eq!(node_id_of(snowflake), u16::MAX);
+        assert_eq!(api_version_of(snowflake), u8::MAX);
+        assert_eq!(model_type_of(snowflake), 0);
+        // the node ends right where the API version starts, and nothing else is set below the counter
+        let node_bits = (u16::MAX as u128) << NODE_ID_SHIFT;
+        let api_version_bits = (u8::MAX as u128) << API_VERSION_SHIFT;
+        assert_eq!(node_bits & api_version_bits, 0);
+        assert_eq!(node_bits.trailing_zeros(), NODE_ID_SHIFT);
+        assert_eq!(node_bits.leading_zeros(), 128 - API_VERSION_SHIFT);
+        assert_eq!(
+            snowflake & ((1 << COUNTER_SHIFT) - 1),
+            node_bits | api_version_bits
+        );
+    }
+
+    #[test]
+    fn sequence_is_used_verbatim() {
+        let ids: Vec<_> = with_frozen_time(1_234, || {
+            (0..1_000)
+                .map(|seq| generate_with_sequence::<3>(4, 5, seq * 65))
+                .collect()
+        });
+        for (seq, &id) in (0..).zip(&ids) {
+            assert_eq!(counter_of(id), seq * 65);
+            assert_eq!(timestamp_of(id), 1_234);
+            assert_eq!(model_type_of(id), 4);
+            assert_eq!(api_version_of(id), 3);
+            assert_eq!(node_id_of(id), 5);
+        }
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn runtime_api_version_matches_const() {
+        let (snowflake, snowflake_dyn) = with_frozen_time(1_234, || {
+            (
+                generate_snowflake::<7>(4, 5),
+                generate_snowflake_dyn(7, 4, 5),
+            )
+        });
+        assert_eq!(api_version_of(snowflake), 7);
+        assert_eq!(api_version_of(snowflake_dyn), api_version_of(snowflake));
+        let parts = decode_snowflake(snowflake);
+        let parts_dyn = decode_snowflake(snowflake_dyn);
+        assert_eq!(parts_dyn.timestamp_ms, parts.timestamp_ms);
+        assert_eq!(parts_dyn.model_type, parts.model_type);
+        assert_eq!(parts_dyn.node_id, parts.node_id);
+    }
+
+    #[test]
+    fn generated_snowflakes_have_clean_reserved_bits() {
+        for _ in 0..1_000 {
+            let id = generate_snowflake_dyn(u8::MAX, u8::MAX, u16::MAX);
+            debug_assert_unused_clear(id, (1 << NODE_ID_SHIFT) - 1);
+            assert_eq!(reserved_of(id), 0);
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "has unused bits 0x1 set")]
+    fn dirty_reserved_bits_are_caught() {
+        let corrupt = pack_with_counter::<0>(1, 0, 0, 0) | 1;
+        debug_assert_unused_clear(corrupt, RESERVED_MASK);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "has unused bits 0x8000 set")]
+    fn unpacked_flags_are_caught() {
+        let corrupt = pack_with_counter::<0>(1, 0, 0, 0) | SYSTEM_FLAG;
+        debug_assert_unused_clear(corrupt, (1 << NODE_ID_SHIFT) - 1);
+    }
+
+    #[test]
+    fn time_safety_checks_introspection() {
+        assert_eq!(
+            TIME_SAFETY_CHECKS_ENABLED,
+            cfg!(feature = "time-safety-checks")
+        );
+        // the rollback checks only track this with the feature on
+        #[cfg(feature = "time-safety-checks")]
+        {
+            generate_snowflake::<0>(0, 0);
+            assert!(LAST_TIME_CREATED.load(Ordering::Relaxed) > 0);
+        }
+    }
+
     #[test]
     fn all_unequal_snowflakes() {
         let mut seen = HashSet::with_capacity(1_000_000);
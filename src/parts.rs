@@ -0,0 +1,376 @@
+use crate::{
+    api_version_of, counter_of, format_version_of, is_system, model_type_of, node_id_of,
+    pack_fields, shard_of, timestamp_of, SnowflakeError, FORMAT_VERSION_SHIFT, MAX_FORMAT_VERSION,
+    MAX_SHARD, MAX_TIMESTAMP, SHARD_SHIFT, SYSTEM_FLAG,
+};
+
+/// Every field of a snowflake except the reserved bits, unpacked.
+///
+/// More fields may be added as the format grows, so this can't be built with a struct literal
+/// outside of this crate: use [`SnowflakeParts::builder`] or [`decode_snowflake`] instead.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{decode_snowflake, SnowflakeParts};
+/// let parts = SnowflakeParts::builder().timestamp_ms(5).model_type(1).build();
+/// assert_eq!(decode_snowflake(parts.pack().unwrap()), parts);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct SnowflakeParts {
+    /// Milliseconds since the Ferris Epoch.
+    pub timestamp_ms: u128,
+    /// The type of model (i.e. user, channel, guild).
+    pub model_type: u8,
+    /// The internal counter.
+    pub counter: u16,
+    /// The API version.
+    pub api_version: u8,
+    /// The node.
+    pub node_id: u16,
+    /// Whether the snowflake is for a system-generated entity.
+    pub system: bool,
+    /// The shard the snowflake routes to.
+    pub shard: u8,
+    /// The version of the format the snowflake is laid out in.
+    pub format_version: u8,
+}
+
+impl SnowflakeParts {
+    /// Returns a builder for a snowflake with every field zeroed.
+    #[inline]
+    pub fn builder() -> SnowflakePartsBuilder {
+        SnowflakePartsBuilder::default()
+    }
+
+    /// Packs these fields into a snowflake.
+    ///
+    /// # Errors
+    /// Returns [`SnowflakeError::TimestampOverflow`] if `timestamp_ms` doesn't fit in the
+    /// timestamp field, [`SnowflakeError::ShardOverflow`] if `shard` doesn't fit in the shard
+    /// field, or [`SnowflakeError::FormatVersionOverflow`] if `format_version` doesn't fit in the
+    /// format version field.
+    #[inline]
+    pub const fn pack(&self) -> Result<u128, SnowflakeError> {
+        if self.timestamp_ms > MAX_TIMESTAMP {
+            return Err(SnowflakeError::TimestampOverflow);
+        }
+        if self.shard > MAX_SHARD {
+            return Err(SnowflakeError::ShardOverflow);
+        }
+        if self.format_version > MAX_FORMAT_VERSION {
+            return Err(SnowflakeError::FormatVersionOverflow);
+        }
+        let id = pack_fields(
+            self.timestamp_ms,
+            self.model_type,
+            self.counter,
+            self.api_version,
+            self.node_id,
+        );
+        let system = if self.system { SYSTEM_FLAG } else { 0 };
+        Ok(id
+            | system
+            | (self.shard as u128) << SHARD_SHIFT
+            | (self.format_version as u128) << FORMAT_VERSION_SHIFT)
+    }
+}
+
+impl From<u128> for SnowflakeParts {
+    #[inline]
+    fn from(id: u128) -> Self {
+        decode_snowflake(id)
+    }
+}
+
+/// A builder for [`SnowflakeParts`], created by [`SnowflakeParts::builder`].
+#[derive(Debug, Clone, Copy, Default)]
+#[must_use]
+pub struct SnowflakePartsBuilder {
+    parts: SnowflakeParts,
+}
+
+impl SnowflakePartsBuilder {
+    /// Sets the timestamp, in milliseconds since the Ferris Epoch.
+    #[inline]
+    pub const fn timestamp_ms(mut self, timestamp_ms: u128) -> Self {
+        self.parts.timestamp_ms = timestamp_ms;
+        self
+    }
+
+    /// Sets the model type.
+    #[inline]
+    pub const fn model_type(mut self, model_type: u8) -> Self {
+        self.parts.model_type = model_type;
+        self
+    }
+
+    /// Sets the internal counter.
+    #[inline]
+    pub const fn counter(mut self, counter: u16) -> Self {
+        self.parts.counter = counter;
+        self
+    }
+
+    /// Sets the API version.
+    #[inline]
+    pub const fn api_version(mut self, api_version: u8) -> Self {
+        self.parts.api_version = api_version;
+        self
+    }
+
+    /// Sets the node.
+    #[inline]
+    pub const fn node_id(mut self, node_id: u16) -> Self {
+        self.parts.node_id = node_id;
+        self
+    }
+
+    /// Sets whether the snowflake is for a system-generated entity.
+    #[inline]
+    pub const fn system(mut self, system: bool) -> Self {
+        self.parts.system = system;
+        self
+    }
+
+    /// Sets the shard.
+    #[inline]
+    pub const fn shard(mut self, shard: u8) -> Self {
+        self.parts.shard = shard;
+        self
+    }
+
+    /// Sets the format version.
+    #[inline]
+    pub const fn format_version(mut self, format_version: u8) -> Self {
+        self.parts.format_version = format_version;
+        self
+    }
+
+    /// Returns the fields set so far.
+    #[inline]
+    #[must_use]
+    pub const fn build(self) -> SnowflakeParts {
+        self.parts
+    }
+}
+
+/// Unpacks every field of a snowflake.
+///
+/// Only format version 0 has been defined so far, so every snowflake is unpacked with its
+/// layout, whatever its format version says. The version is kept in `format_version`, so callers
+/// can refuse snowflakes tagged with a version they don't know.
+#[inline]
+pub const fn decode_snowflake(id: u128) -> SnowflakeParts {
+    SnowflakeParts {
+        timestamp_ms: timestamp_of(id),
+        model_type: model_type_of(id),
+        counter: counter_of(id),
+        api_version: api_version_of(id),
+        node_id: node_id_of(id),
+        system: is_system(id),
+        shard: shard_of(id),
+        format_version: format_version_of(id),
+    }
+}
+
+/// How far the counter field was shifted left in the 2.x layout.
+const LEGACY_COUNTER_SHIFT: u32 = 42;
+/// How far the API version field was shifted left in the 2.x layout.
+const LEGACY_API_VERSION_SHIFT: u32 = 34;
+/// How far the node ID field was shifted left in the 2.x layout.
+const LEGACY_NODE_ID_SHIFT: u32 = 18;
+
+/// Unpacks a snowflake generated by version 2.x of this crate, whose layout differs from the
+/// current one below the model type.
+///
+/// The 2.x counter's top two bits overlapped the model type and were added into it, so they
+/// can't be recovered: `counter` holds only the low 14 bits of the counter, and `model_type`
+/// (and, if adding them carried, `timestamp_ms`) may be off by the top two bits. The API version
+/// and node decode exactly, and the fields 2.x didn't have are left unset.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::decode_legacy;
+/// // generated by 2.x for API version 1, model type 2 and node 3
+/// let parts = decode_legacy(0x0000_0000_0526_5C00_0200_1004_000C_0000);
+/// assert_eq!((parts.api_version, parts.model_type, parts.node_id), (1, 2, 3));
+/// assert_eq!(parts.counter, 4);
+/// ```
+#[inline]
+pub const fn decode_legacy(id: u128) -> SnowflakeParts {
+    SnowflakeParts {
+        timestamp_ms: timestamp_of(id),
+        model_type: model_type_of(id),
+        counter: (id >> LEGACY_COUNTER_SHIFT) as u16 & 0x3FFF,
+        api_version: (id >> LEGACY_API_VERSION_SHIFT) as u8,
+        node_id: (id >> LEGACY_NODE_ID_SHIFT) as u16,
+        system: false,
+        shard: 0,
+        format_version: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pack_with_counter, reserved_of};
+
+    /// Reference vectors for the bit layout, as `(parts, snowflake)`.
+    ///
+    /// These pin the format down as a contract: they must never change, and other
+    /// implementations of the format can check themselves against them.
+    #[rustfmt::skip]
+    const REFERENCE_VECTORS: &[(SnowflakeParts, u128)] = &[
+        (parts(0x0, 0x00, 0x0000, 0x00, 0x0000, false, 0, 0), 0x0000_0000_0000_0000_0000_0000_0000_0000),
+        (parts(0xFFFF_FFFF_FFFF_FFFF, 0x00, 0x0000, 0x00, 0x0000, false, 0, 0), 0xFFFF_FFFF_FFFF_FFFF_0000_0000_0000_0000),
+        (parts(0x0, 0xFF, 0x0000, 0x00, 0x0000, false, 0, 0), 0x0000_0000_0000_0000_FF00_0000_0000_0000),
+        (parts(0x0, 0x00, 0xFFFF, 0x00, 0x0000, false, 0, 0), 0x0000_0000_0000_0000_00FF_FF00_0000_0000),
+        (parts(0x0, 0x00, 0x0000, 0xFF, 0x0000, false, 0, 0), 0x0000_0000_0000_0000_0000_00FF_0000_0000),
+        (parts(0x0, 0x00, 0x0000, 0x00, 0xFFFF, false, 0, 0), 0x0000_0000_0000_0000_0000_0000_FFFF_0000),
+        (parts(0x0, 0x00, 0x0000, 0x00, 0x0000, true, 0, 0), 0x0000_0000_0000_0000_0000_0000_0000_8000),
+        (parts(0x0, 0x00, 0x0000, 0x00, 0x0000, false, 0xF, 0), 0x0000_0000_0000_0000_0000_0000_0000_7800),
+        (parts(0x0, 0x00, 0x0000, 0x00, 0x0000, false, 0, 0x3), 0x0000_0000_0000_0000_0000_0000_0000_0600),
+        (parts(0xFFFF_FFFF_FFFF_FFFF, 0xFF, 0xFFFF, 0xFF, 0xFFFF, true, 0xF, 0x3), 0xFFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FE00),
+        (parts(0x1, 0x01, 0x0001, 0x01, 0x0001, true, 0x1, 0x1), 0x0000_0000_0000_0001_0100_0101_0001_8A00),
+        (parts(0x0123_4567_89AB_CDEF, 0x12, 0x3456, 0x78, 0x9ABC, false, 0xA, 0x2), 0x0123_4567_89AB_CDEF_1234_5678_9ABC_5400),
+        (parts(0x0526_5C00, 0x03, 0x002A, 0x02, 0x0007, false, 0, 0), 0x0000_0000_0526_5C00_0300_2A02_0007_0000),
+    ];
+
+    /// Builds [`SnowflakeParts`] for [`REFERENCE_VECTORS`].
+    #[allow(clippy::too_many_arguments)]
+    const fn parts(
+        timestamp_ms: u128,
+        model_type: u8,
+        counter: u16,
+        api_version: u8,
+        node_id: u16,
+        system: bool,
+        shard: u8,
+        format_version: u8,
+    ) -> SnowflakeParts {
+        SnowflakeParts {
+            timestamp_ms,
+            model_type,
+            counter,
+            api_version,
+            node_id,
+            system,
+            shard,
+            format_version,
+        }
+    }
+
+    #[test]
+    fn reference_vectors_pack() {
+        for &(parts, expected) in REFERENCE_VECTORS {
+            assert_eq!(parts.pack(), Ok(expected), "packing {:?}", parts);
+        }
+    }
+
+    #[test]
+    fn reference_vectors_decode() {
+        for &(parts, id) in REFERENCE_VECTORS {
+            assert_eq!(decode_snowflake(id), parts, "decoding {:#X}", id);
+            assert_eq!(is_system(id), parts.system, "decoding {:#X}", id);
+            assert_eq!(shard_of(id), parts.shard, "decoding {:#X}", id);
+            assert_eq!(
+                format_version_of(id),
+                parts.format_version,
+                "decoding {:#X}",
+                id
+            );
+            assert_eq!(reserved_of(id), 0, "decoding {:#X}", id);
+        }
+    }
+
+    #[test]
+    fn future_format_versions_decode() {
+        let id = pack_with_counter::<2>(1_234, 3, 4, 5);
+        for format_version in 1..=MAX_FORMAT_VERSION {
+            let tagged = id | (format_version as u128) << FORMAT_VERSION_SHIFT;
+            let parts = decode_snowflake(tagged);
+            assert_eq!(parts.format_version, format_version);
+            assert_eq!(
+                parts,
+                SnowflakeParts {
+                    format_version,
+                    ..decode_snowflake(id)
+                }
+            );
+            assert_eq!(parts.pack(), Ok(tagged));
+        }
+        let parts = SnowflakeParts::builder()
+            .format_version(MAX_FORMAT_VERSION + 1)
+            .build();
+        assert_eq!(parts.pack(), Err(SnowflakeError::FormatVersionOverflow));
+        let parts = SnowflakeParts::builder().shard(MAX_SHARD + 1).build();
+        assert_eq!(parts.pack(), Err(SnowflakeError::ShardOverflow));
+    }
+
+    #[test]
+    fn legacy_snowflakes_decode() {
+        /// Packs a snowflake the way 2.x did.
+        fn pack_legacy(
+            timestamp_ms: u128,
+            model_type: u8,
+            counter: u16,
+            api: u8,
+            node: u16,
+        ) -> u128 {
+            (timestamp_ms << 64)
+                + ((model_type as u128) << 56)
+                + ((counter as u128) << 42)
+                + ((api as u128) << 34)
+                + ((node as u128) << 18)
+        }
+
+        let parts = decode_legacy(pack_legacy(0x0526_5C00, 2, 0x3FFF, u8::MAX, u16::MAX));
+        assert_eq!(parts.timestamp_ms, 0x0526_5C00);
+        assert_eq!(parts.model_type, 2);
+        assert_eq!(parts.counter, 0x3FFF);
+        assert_eq!(parts.api_version, u8::MAX);
+        assert_eq!(parts.node_id, u16::MAX);
+
+        // the counter's top two bits end up in the model type
+        let parts = decode_legacy(pack_legacy(1_234, 2, 0xC005, 1, 3));
+        assert_eq!(parts.model_type, 2 + 3);
+        assert_eq!(parts.counter, 5);
+        assert_eq!((parts.api_version, parts.node_id), (1, 3));
+
+        // and the current layout gets the fields below the model type wrong
+        let id = pack_legacy(1_234, 2, 4, 1, 3);
+        assert_ne!(decode_snowflake(id).node_id, 3);
+        assert_eq!(decode_legacy(id).node_id, 3);
+    }
+
+    #[test]
+    fn built_parts_round_trip() {
+        let parts = SnowflakeParts::builder()
+            .timestamp_ms(MAX_TIMESTAMP)
+            .model_type(3)
+            .counter(u16::MAX)
+            .api_version(7)
+            .node_id(42)
+            .system(true)
+            .shard(MAX_SHARD)
+            .format_version(MAX_FORMAT_VERSION)
+            .build();
+        assert_eq!(decode_snowflake(parts.pack().unwrap()), parts);
+
+        let parts = SnowflakeParts {
+            timestamp_ms: 5,
+            model_type: 1,
+            ..Default::default()
+        };
+        assert_eq!(SnowflakeParts::from(parts.pack().unwrap()), parts);
+    }
+
+    #[test]
+    fn oversized_timestamp_errors() {
+        let parts = SnowflakeParts::builder()
+            .timestamp_ms(MAX_TIMESTAMP + 1)
+            .build();
+        assert_eq!(parts.pack(), Err(SnowflakeError::TimestampOverflow));
+    }
+}
@@ -0,0 +1,24 @@
+/// What a [`SnowflakeGenerator`](crate::SnowflakeGenerator) does when more snowflakes are
+/// generated in one millisecond than its counter can tell apart.
+///
+/// Exhaustion is detected by counting the snowflakes generated in each millisecond, which is
+/// exact for a single thread and best-effort when generating from many threads at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum CounterPolicy {
+    /// Let the counter wrap around and keep stamping the same millisecond, which produces
+    /// duplicates of snowflakes generated earlier in it. This is the default, and the only option
+    /// that never blocks.
+    #[default]
+    Wrap,
+    /// Busy-spin until the clock reaches the next millisecond, then generate in that one.
+    SpinToNextMs,
+    /// Fail with [`SnowflakeError::CounterExhausted`](crate::SnowflakeError::CounterExhausted).
+    Error,
+    /// Like [`SpinToNextMs`](Self::SpinToNextMs), but backs off under sustained load: once the
+    /// counter has been exhausted in 3 consecutive milliseconds, waiting generations yield their
+    /// thread to the scheduler between clock reads instead of busy-spinning, until a millisecond
+    /// passes without exhausting the counter. This trades a little latency for not pinning a
+    /// core while the clock catches up.
+    AdaptiveSpin,
+}
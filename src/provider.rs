@@ -0,0 +1,88 @@
+use crate::SnowflakeError;
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// A source of node IDs for clusters where nodes join and leave dynamically.
+///
+/// Implement this on top of whatever coordination service the cluster already uses (etcd, Redis,
+/// ZooKeeper, ...) and pass it to [`SnowflakeGenerator::with_provider`], which acquires a node ID
+/// on construction and releases it when the generator is dropped.
+///
+/// [`SnowflakeGenerator::with_provider`]: crate::SnowflakeGenerator::with_provider
+pub trait NodeIdProvider: Send + Sync {
+    /// Reserves a node ID that no other live generator is using.
+    ///
+    /// # Errors
+    /// Returns an error if no node ID could be reserved.
+    fn acquire(&self) -> Result<u16, SnowflakeError>;
+
+    /// Returns a node ID previously handed out by [`acquire`](Self::acquire).
+    fn release(&self, id: u16);
+}
+
+impl<P: NodeIdProvider + ?Sized> NodeIdProvider for Arc<P> {
+    #[inline]
+    fn acquire(&self) -> Result<u16, SnowflakeError> {
+        (**self).acquire()
+    }
+
+    #[inline]
+    fn release(&self, id: u16) {
+        (**self).release(id)
+    }
+}
+
+/// A [`NodeIdProvider`] that hands out node IDs from memory within a single process.
+///
+/// This is mostly useful for testing: it can't coordinate between processes or hosts.
+/// The lowest free node ID is always handed out first.
+#[derive(Debug, Default)]
+pub struct InMemoryNodeIdProvider {
+    in_use: Mutex<BTreeSet<u16>>,
+}
+
+impl InMemoryNodeIdProvider {
+    /// Creates a provider with every node ID free.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl NodeIdProvider for InMemoryNodeIdProvider {
+    fn acquire(&self) -> Result<u16, SnowflakeError> {
+        let mut in_use = self.in_use.lock().unwrap_or_else(PoisonError::into_inner);
+        let id = (0..=u16::MAX)
+            .find(|id| !in_use.contains(id))
+            .ok_or(SnowflakeError::NodeIdsExhausted)?;
+        in_use.insert(id);
+        Ok(id)
+    }
+
+    fn release(&self, id: u16) {
+        self.in_use
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeGenerator;
+
+    #[test]
+    fn generators_get_distinct_node_ids() {
+        let provider = Arc::new(InMemoryNodeIdProvider::new());
+        let generator_1 = SnowflakeGenerator::with_provider(Arc::clone(&provider)).unwrap();
+        let generator_2 = SnowflakeGenerator::with_provider(Arc::clone(&provider)).unwrap();
+        assert_ne!(generator_1.node_id(), generator_2.node_id());
+
+        let released = generator_1.node_id();
+        drop(generator_1);
+        let generator_3 = SnowflakeGenerator::with_provider(Arc::clone(&provider)).unwrap();
+        assert_eq!(generator_3.node_id(), released);
+    }
+}
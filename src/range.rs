@@ -0,0 +1,272 @@
+use crate::{FERRIS_EPOCH, MAX_TIMESTAMP, TIMESTAMP_SHIFT};
+
+/// Converts a time in milliseconds since the Unix Epoch to a snowflake timestamp, clamping times
+/// outside of the range a snowflake can represent.
+#[inline]
+const fn clamped_timestamp(unix_ms: u128) -> u128 {
+    let timestamp = unix_ms.saturating_sub(FERRIS_EPOCH);
+    if timestamp > MAX_TIMESTAMP {
+        MAX_TIMESTAMP
+    } else {
+        timestamp
+    }
+}
+
+/// Returns the smallest snowflake that could have been generated at `unix_ms` milliseconds since
+/// the Unix Epoch.
+///
+/// Times before the Ferris Epoch are treated as the Ferris Epoch itself.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{generate_snowflake, min_for_timestamp, unix_timestamp_ms_of};
+/// let id = generate_snowflake::<0>(0, 0);
+/// assert!(min_for_timestamp(unix_timestamp_ms_of(id)) <= id);
+/// ```
+#[inline]
+pub const fn min_for_timestamp(unix_ms: u128) -> u128 {
+    clamped_timestamp(unix_ms) << TIMESTAMP_SHIFT
+}
+
+/// Returns the largest snowflake that could have been generated at `unix_ms` milliseconds since
+/// the Unix Epoch.
+///
+/// Times before the Ferris Epoch are treated as the Ferris Epoch itself.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{generate_snowflake, max_for_timestamp, unix_timestamp_ms_of};
+/// let id = generate_snowflake::<0>(0, 0);
+/// assert!(max_for_timestamp(unix_timestamp_ms_of(id)) >= id);
+/// ```
+#[inline]
+pub const fn max_for_timestamp(unix_ms: u128) -> u128 {
+    min_for_timestamp(unix_ms) | ((1 << TIMESTAMP_SHIFT) - 1)
+}
+
+/// Returns a pagination cursor such that `id > cursor_after(unix_ms)` holds exactly for the
+/// snowflakes generated in or after millisecond `unix_ms` (since the Unix Epoch).
+///
+/// This is the largest snowflake that could have been generated before that millisecond, one
+/// less than [`min_for_timestamp`]. The smallest snowflake of the millisecond itself would make
+/// the `>` comparison leave that snowflake out, and it's a real one: the first snowflake
+/// [`generate_snowflake`](crate::generate_snowflake) hands out in a millisecond with model type,
+/// API version and node 0. Times at or before the Ferris Epoch give 0.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{
+///     cursor_after, cursor_before, generate_snowflake, unix_timestamp_ms_of,
+/// };
+/// let id = generate_snowflake::<0>(0, 0);
+/// let unix_ms = unix_timestamp_ms_of(id);
+/// assert!(id > cursor_after(unix_ms));
+/// assert!(id >= cursor_before(unix_ms));
+/// assert!(id < cursor_before(unix_ms + 1));
+/// ```
+#[inline]
+pub const fn cursor_after(unix_ms: u128) -> u128 {
+    min_for_timestamp(unix_ms).saturating_sub(1)
+}
+
+/// Returns a pagination cursor such that `id < cursor_before(unix_ms)` holds exactly for the
+/// snowflakes generated before millisecond `unix_ms` (since the Unix Epoch).
+///
+/// This is the smallest snowflake that could have been generated in that millisecond.
+#[inline]
+pub const fn cursor_before(unix_ms: u128) -> u128 {
+    min_for_timestamp(unix_ms)
+}
+
+/// How many milliseconds there are in a day.
+const DAY_MS: i128 = 24 * 60 * 60 * 1_000;
+
+/// Returns how many days `year`-`month`-`day` (in the proleptic Gregorian calendar) is after the
+/// Unix Epoch, or before it if negative.
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>.
+const fn days_from_civil(year: i32, month: u8, day: u8) -> i64 {
+    let year = year as i64 - (month <= 2) as i64;
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Returns how many days there are in `month` of `year`.
+const fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Returns the smallest and largest snowflakes that could have been generated on the UTC day
+/// `year`-`month`-`day`, so that `id BETWEEN min AND max` selects exactly that day's snowflakes.
+///
+/// Returns `None` for days wholly outside of the range a snowflake can represent, such as any
+/// day before the Ferris Epoch: no snowflake could have been generated on them, and clamping
+/// them like [`min_for_timestamp`] and [`max_for_timestamp`] do would select snowflakes from
+/// other days.
+///
+/// # Panics
+/// Panics if the date doesn't exist.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{day_range, pack_with_counter};
+/// let (min, max) = day_range(2022, 1, 1).unwrap();
+/// assert!((min..=max).contains(&pack_with_counter::<0>(0, 0, 0, 0)));
+/// assert_eq!(day_range(2021, 12, 31), None);
+/// ```
+pub fn day_range(year: i32, month: u8, day: u8) -> Option<(u128, u128)> {
+    assert!((1..=12).contains(&month), "there is no month {}", month);
+    assert!(
+        (1..=days_in_month(year, month)).contains(&day),
+        "there is no {}-{:02}-{:02}",
+        year,
+        month,
+        day
+    );
+    let start = days_from_civil(year, month, day) as i128 * DAY_MS;
+    let end = start + DAY_MS - 1;
+    if end < FERRIS_EPOCH as i128 || start > (FERRIS_EPOCH + MAX_TIMESTAMP) as i128 {
+        return None;
+    }
+    Some((
+        min_for_timestamp(start as u128),
+        max_for_timestamp(end as u128),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        pack_with_counter, unix_timestamp_ms_of, SnowflakeGenerator, FORMAT_VERSION_SHIFT,
+        MAX_FORMAT_VERSION, MAX_SHARD, RESERVED_MASK, SHARD_SHIFT, SYSTEM_FLAG,
+    };
+
+    /// Converts days since the Unix Epoch back to a date, the inverse of `days_from_civil`.
+    fn civil_from_days(days: i64) -> (i32, u8, u8) {
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u8;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+        let year = (year_of_era + era * 400 + (month <= 2) as i64) as i32;
+        (year, month, day)
+    }
+
+    /// Returns the [`day_range`] of the day `days` days after the Unix Epoch.
+    fn day_range_of(days: i64) -> Option<(u128, u128)> {
+        let (year, month, day) = civil_from_days(days);
+        day_range(year, month, day)
+    }
+
+    #[test]
+    fn timestamp_bounds_contain_every_snowflake() {
+        let unix_ms = FERRIS_EPOCH + 1_234;
+        let smallest = pack_with_counter::<0>(1_234, 0, 0, 0);
+        let largest = pack_with_counter::<{ u8::MAX }>(1_234, u8::MAX, u16::MAX, u16::MAX)
+            | SYSTEM_FLAG
+            | (MAX_SHARD as u128) << SHARD_SHIFT
+            | (MAX_FORMAT_VERSION as u128) << FORMAT_VERSION_SHIFT
+            | RESERVED_MASK;
+        assert_eq!(min_for_timestamp(unix_ms), smallest);
+        assert_eq!(max_for_timestamp(unix_ms), largest);
+        assert_eq!(
+            max_for_timestamp(unix_ms) + 1,
+            min_for_timestamp(unix_ms + 1)
+        );
+
+        assert_eq!(min_for_timestamp(0), 0);
+        assert_eq!(
+            min_for_timestamp(u128::MAX),
+            MAX_TIMESTAMP << TIMESTAMP_SHIFT
+        );
+        assert_eq!(max_for_timestamp(u128::MAX), u128::MAX);
+    }
+
+    #[test]
+    fn day_range_covers_the_whole_day() {
+        // 2022-01-01T00:00:00Z is the Ferris Epoch
+        let (min, max) = day_range(2022, 1, 1).unwrap();
+        assert_eq!(min, min_for_timestamp(FERRIS_EPOCH));
+        assert_eq!(max, max_for_timestamp(FERRIS_EPOCH + DAY_MS as u128 - 1));
+        assert_eq!(max + 1, day_range(2022, 1, 2).unwrap().0);
+        assert_eq!(
+            day_range(2024, 2, 29).unwrap().1 + 1,
+            day_range(2024, 3, 1).unwrap().0
+        );
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+    }
+
+    #[test]
+    fn day_range_skips_days_snowflakes_cant_represent() {
+        // the day before the Ferris Epoch would otherwise cover the epoch's first millisecond
+        assert_eq!(day_range(2021, 12, 31), None);
+        assert_eq!(day_range(1970, 1, 1), None);
+        assert_eq!(day_range(i32::MAX, 12, 31), None);
+
+        // the last representable day is cut short at the last representable millisecond
+        let last_ms = FERRIS_EPOCH + MAX_TIMESTAMP;
+        let last_day = (last_ms / DAY_MS as u128) as i64;
+        let (min, max) = day_range_of(last_day).unwrap();
+        assert_eq!(min, min_for_timestamp(last_day as u128 * DAY_MS as u128));
+        assert_eq!(max, u128::MAX);
+        assert_eq!(day_range_of(last_day + 1), None);
+    }
+
+    #[test]
+    fn todays_snowflakes_are_in_todays_range() {
+        let id = SnowflakeGenerator::new(0).generate::<0>(0);
+        let today = unix_timestamp_ms_of(id) as i64 / DAY_MS as i64;
+        let (year, month, day) = civil_from_days(today);
+        assert_eq!(days_from_civil(year, month, day), today);
+        let (min, max) = day_range(year, month, day).unwrap();
+        assert!((min..=max).contains(&id));
+        let (min, max) = day_range_of(today - 1).unwrap();
+        assert!(!(min..=max).contains(&id));
+    }
+
+    #[test]
+    #[should_panic(expected = "there is no 2023-02-29")]
+    fn day_range_rejects_missing_days() {
+        let _ = day_range(2023, 2, 29);
+    }
+
+    #[test]
+    fn cursors_split_pages_at_the_millisecond() {
+        let earlier = pack_with_counter::<3>(999, 4, u16::MAX, 5);
+        let at = pack_with_counter::<3>(1_000, 4, 6, 5);
+        let later = pack_with_counter::<3>(1_001, 4, 0, 5);
+        let unix_ms = unix_timestamp_ms_of(at);
+
+        let after = cursor_after(unix_ms);
+        assert!(later > after);
+        assert!(at > after);
+        assert!(earlier <= after);
+
+        let before = cursor_before(unix_ms);
+        assert!(earlier < before);
+        assert!(at >= before);
+        assert!(later >= before);
+
+        // the two cursors split every snowflake into the pages before and after the millisecond
+        assert_eq!(after + 1, before);
+        let first = pack_with_counter::<0>(1_000, 0, 0, 0);
+        assert_eq!(first, min_for_timestamp(unix_ms));
+        assert!(first > after);
+        assert_eq!(cursor_after(FERRIS_EPOCH), 0);
+    }
+}
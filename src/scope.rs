@@ -0,0 +1,101 @@
+use crate::{timestamp_of, Counter, SnowflakeGenerator, TIMESTAMP_SHIFT};
+use std::cmp::Ordering;
+
+/// Issues snowflakes from a [`SnowflakeGenerator`] that sort in the order they were issued, such
+/// as for the entities created while handling one request.
+///
+/// The generator's counter already orders snowflakes within a millisecond, but not once it wraps
+/// around, nor if the clock steps backwards. When a snowflake from the generator wouldn't sort
+/// after the last one issued by the scope, the scope rolls its timestamp forward to the last
+/// one's millisecond, or the one after that if its counter is behind too. Rolled forward
+/// snowflakes keep the counter value the generator issued, so they only collide with another
+/// snowflake if the generator issues that counter value again by the time it reaches the same
+/// millisecond.
+///
+/// Created with [`SnowflakeGenerator::request_scope`].
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::SnowflakeGenerator;
+/// let generator = SnowflakeGenerator::new(0);
+/// let mut scope = generator.request_scope();
+/// let (first, second) = (scope.next::<0>(0), scope.next::<0>(0));
+/// assert!(first < second);
+/// ```
+pub struct RequestScope<'a, C: Counter> {
+    /// The generator snowflakes are issued from.
+    generator: &'a SnowflakeGenerator<C>,
+    /// The last snowflake this scope issued.
+    last: Option<u128>,
+}
+
+impl<'a, C: Counter> RequestScope<'a, C> {
+    #[inline]
+    pub(crate) const fn new(generator: &'a SnowflakeGenerator<C>) -> Self {
+        Self {
+            generator,
+            last: None,
+        }
+    }
+
+    /// Generates a snowflake from the current API version and the model type that sorts after
+    /// every snowflake this scope issued before it, by timestamp and then by counter.
+    ///
+    /// # Panics
+    /// Panics under the same conditions as [`SnowflakeGenerator::generate`].
+    #[must_use]
+    pub fn next<const API_VERSION: u8>(&mut self, model_type: u8) -> u128 {
+        let mut id = self.generator.generate::<API_VERSION>(model_type);
+        if let Some(last) = self.last {
+            if SnowflakeGenerator::<C>::chronological_cmp(id, last) != Ordering::Greater {
+                let rolled = with_timestamp(id, timestamp_of(last));
+                id = if SnowflakeGenerator::<C>::chronological_cmp(rolled, last)
+                    == Ordering::Greater
+                {
+                    rolled
+                } else {
+                    with_timestamp(id, timestamp_of(last) + 1)
+                };
+            }
+        }
+        self.last = Some(id);
+        id
+    }
+}
+
+/// Replaces the timestamp of a snowflake, leaving every other field untouched.
+#[inline]
+const fn with_timestamp(id: u128, timestamp: u128) -> u128 {
+    (timestamp << TIMESTAMP_SHIFT) | (id & ((1 << TIMESTAMP_SHIFT) - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chronological_cmp, FERRIS_EPOCH};
+    use std::collections::HashSet;
+
+    #[test]
+    fn scoped_snowflakes_are_strictly_ordered() {
+        let generator = SnowflakeGenerator::new(0);
+        let mut scope = generator.request_scope();
+        let ids: Vec<_> = (0..100_000).map(|_| scope.next::<0>(0)).collect();
+        assert!(ids
+            .windows(2)
+            .all(|pair| chronological_cmp(pair[0], pair[1]) == Ordering::Less));
+    }
+
+    #[test]
+    fn scope_rolls_forward_past_a_wrapped_counter() {
+        // with the clock stuck, the counter wraps around within the same millisecond
+        let generator = SnowflakeGenerator::new(0).with_clock(|| FERRIS_EPOCH + 1_000);
+        let mut scope = generator.request_scope();
+        let ids: Vec<_> = (0..100_000).map(|_| scope.next::<0>(0)).collect();
+        assert!(ids
+            .windows(2)
+            .all(|pair| chronological_cmp(pair[0], pair[1]) == Ordering::Less));
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), ids.len());
+        assert_eq!(timestamp_of(ids[(1 << 16) - 1]), 1_000);
+        assert_eq!(timestamp_of(ids[1 << 16]), 1_001);
+    }
+}
@@ -0,0 +1,75 @@
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Reads a seed from the operating system's entropy source.
+pub(crate) fn entropy_seed() -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(u32::from_ne_bytes(bytes))
+}
+
+/// Derives a seed from the low bits of the current time in nanoseconds, the process ID and how
+/// many seeds this process has derived so far.
+///
+/// This is only meant as a fallback for when there is no entropy to be had: anyone who knows
+/// roughly when a generator was created can guess its seed. The process ID is left out on
+/// WebAssembly, where asking for it panics.
+pub(crate) fn time_seed() -> u32 {
+    /// How many seeds have been derived, so that two derived in the same nanosecond differ.
+    static DERIVED: AtomicU32 = AtomicU32::new(0);
+
+    #[cfg(not(feature = "wasm"))]
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    // SystemTime isn't available, so this is only as precise as the time source
+    #[cfg(feature = "wasm")]
+    let nanos = crate::Clock::now_ms(&crate::SystemClock) * 1_000_000;
+    let mut seed = nanos as u64 ^ DERIVED.fetch_add(1, Ordering::Relaxed) as u64;
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        seed ^= (std::process::id() as u64) << 32;
+    }
+    // splitmix64's finalizer, so that every input bit affects every seed bit
+    seed = (seed ^ (seed >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    seed = (seed ^ (seed >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (seed ^ (seed >> 31)) as u32
+}
+
+/// Returns a seed from `entropy`, falling back to [`time_seed`] if it fails, and logs which one
+/// was used.
+pub(crate) fn counter_seed(entropy: impl FnOnce() -> io::Result<u32>) -> u32 {
+    match entropy() {
+        Ok(seed) => {
+            eprintln!("seeded the snowflake counter from the system's entropy source");
+            seed
+        }
+        Err(error) => {
+            eprintln!(
+                "couldn't read entropy to seed the snowflake counter ({}), seeding it from the \
+                 clock instead",
+                error
+            );
+            time_seed()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn failing_entropy_falls_back_to_time() {
+        let seeds: HashSet<_> = (0..8)
+            .map(|_| counter_seed(|| Err(io::Error::other("no entropy here"))))
+            .collect();
+        assert_eq!(seeds.len(), 8);
+    }
+
+    #[test]
+    fn entropy_is_used_when_available() {
+        assert_eq!(counter_seed(|| Ok(42)), 42);
+    }
+}
@@ -0,0 +1,199 @@
+use crate::chronological_cmp;
+use std::cmp::Ordering;
+use std::fmt;
+use std::num::TryFromIntError;
+
+/// A snowflake, wrapped to keep it from being mixed up with other integers.
+///
+/// Snowflakes order by the time they were generated, then by the rest of their fields.
+///
+/// This is guaranteed to have the same layout and ABI as a `u128`, so it can be passed across FFI
+/// boundaries as one, and with the `ffi` feature, `Snowflake::as_u128_slice` reinterprets a
+/// `&[Snowflake]` as a `&[u128]` without copying.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[repr(transparent)]
+pub struct Snowflake(pub u128);
+
+impl Snowflake {
+    /// Generates a snowflake for API version 0 and model type 0 from the [`global`](crate::global)
+    /// generator.
+    ///
+    /// This is meant for quick scripts: unless [`init_global`](crate::init_global) configured it,
+    /// the global generator stamps every snowflake with node 0, so production code should
+    /// configure a node ID explicitly.
+    ///
+    /// # Panics
+    /// Panics if the current time is behind the Unix Epoch.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use ferrischat_snowflake_generator::Snowflake;
+    /// assert_ne!(Snowflake::next(), Snowflake::next());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn next() -> Self {
+        Self(crate::global().generate::<0>(0))
+    }
+}
+
+#[cfg(feature = "ffi")]
+impl Snowflake {
+    /// Reinterprets a slice of snowflakes as a slice of `u128`s, without copying.
+    ///
+    /// This is only available with the `ffi` feature.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use ferrischat_snowflake_generator::Snowflake;
+    /// let snowflakes = [Snowflake(1), Snowflake(2)];
+    /// assert_eq!(Snowflake::as_u128_slice(&snowflakes), [1, 2]);
+    /// ```
+    #[inline]
+    #[must_use]
+    #[allow(unsafe_code)]
+    pub fn as_u128_slice(snowflakes: &[Snowflake]) -> &[u128] {
+        // SAFETY: `Snowflake` is `repr(transparent)` over a `u128`, so it has the same size,
+        // alignment and valid values, and the new slice borrows the same memory for the same
+        // lifetime, with the same length
+        unsafe { std::slice::from_raw_parts(snowflakes.as_ptr().cast::<u128>(), snowflakes.len()) }
+    }
+}
+
+impl fmt::Display for Snowflake {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl From<u128> for Snowflake {
+    #[inline]
+    fn from(id: u128) -> Self {
+        Self(id)
+    }
+}
+
+impl From<Snowflake> for u128 {
+    #[inline]
+    fn from(snowflake: Snowflake) -> Self {
+        snowflake.0
+    }
+}
+
+impl From<Snowflake> for String {
+    /// Formats the snowflake in decimal.
+    #[inline]
+    fn from(snowflake: Snowflake) -> Self {
+        snowflake.to_string()
+    }
+}
+
+impl TryFrom<Snowflake> for i64 {
+    type Error = TryFromIntError;
+
+    /// Converts the snowflake to an `i64`, which only succeeds for snowflakes from the very start
+    /// of the Ferris Epoch since the timestamp is stored in the high bits.
+    #[inline]
+    fn try_from(snowflake: Snowflake) -> Result<Self, Self::Error> {
+        i64::try_from(snowflake.0)
+    }
+}
+
+/// A snowflake that a [`BinaryHeap`](std::collections::BinaryHeap) pops newest first.
+///
+/// `BinaryHeap` always pops its greatest element, so this orders snowflakes such that the newest
+/// one is the greatest, as ordered by [`chronological_cmp`]: by timestamp, then by counter,
+/// regardless of model type. That also means that sorting these in ascending order, or iterating
+/// a `BTreeSet` of them, yields the oldest first, like it would for plain snowflakes.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{NewestFirst, Snowflake};
+/// use std::collections::BinaryHeap;
+/// let (older, newer) = (Snowflake::next(), Snowflake::next());
+/// let mut heap = BinaryHeap::from([NewestFirst(older), NewestFirst(newer)]);
+/// assert_eq!(heap.pop(), Some(NewestFirst(newer)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NewestFirst(pub Snowflake);
+
+impl Ord for NewestFirst {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        chronological_cmp(self.0 .0, other.0 .0)
+    }
+}
+
+impl PartialOrd for NewestFirst {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_snowflake;
+
+    #[test]
+    fn next_is_unique() {
+        let (snowflake_1, snowflake_2) = (Snowflake::next(), Snowflake::next());
+        assert_ne!(snowflake_1, snowflake_2);
+        assert_eq!(crate::node_id_of(snowflake_1.0), crate::global().node_id());
+    }
+
+    #[test]
+    fn heap_pops_newest_first() {
+        use crate::pack_with_counter;
+        use std::collections::BinaryHeap;
+
+        // the model type sorts before the counter as an integer, but not chronologically
+        let oldest = pack_with_counter::<0>(1_000, 9, 0, 0);
+        let older = pack_with_counter::<0>(1_000, 0, 1, 0);
+        let newer = pack_with_counter::<0>(1_001, 5, 0, 0);
+        let newest = pack_with_counter::<0>(1_002, 0, 0, 0);
+        let mut heap: BinaryHeap<_> = [newer, oldest, newest, older]
+            .into_iter()
+            .map(|id| NewestFirst(Snowflake(id)))
+            .collect();
+        let popped: Vec<_> = std::iter::from_fn(|| heap.pop())
+            .map(|NewestFirst(snowflake)| snowflake.0)
+            .collect();
+        assert_eq!(popped, [newest, newer, older, oldest]);
+    }
+
+    #[test]
+    fn layout_matches_u128() {
+        use std::mem::{align_of, size_of};
+        assert_eq!(size_of::<Snowflake>(), size_of::<u128>());
+        assert_eq!(align_of::<Snowflake>(), align_of::<u128>());
+        assert_eq!(size_of::<[Snowflake; 3]>(), size_of::<[u128; 3]>());
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn slices_reinterpret_as_u128() {
+        let ids: Vec<_> = (0..100).map(|_| generate_snowflake::<0>(0, 0)).collect();
+        let snowflakes: Vec<_> = ids.iter().copied().map(Snowflake).collect();
+        let reinterpreted = Snowflake::as_u128_slice(&snowflakes);
+        assert_eq!(reinterpreted, ids.as_slice());
+        assert_eq!(reinterpreted.as_ptr().cast(), snowflakes.as_ptr());
+        assert!(Snowflake::as_u128_slice(&[]).is_empty());
+    }
+
+    #[test]
+    fn outward_conversions() {
+        let id = generate_snowflake::<0>(0, 0);
+        assert_eq!(u128::from(Snowflake(id)), id);
+        assert_eq!(String::from(Snowflake(id)), id.to_string());
+        assert_eq!(i64::try_from(Snowflake(12_345)), Ok(12_345));
+    }
+
+    #[test]
+    fn oversized_i64_conversion_errors() {
+        assert!(i64::try_from(Snowflake(i64::MAX as u128 + 1)).is_err());
+        assert!(i64::try_from(Snowflake(generate_snowflake::<0>(0, 0))).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{current_time, SnowflakeError, FERRIS_EPOCH};
+use std::fmt;
+use std::sync::atomic::{AtomicU16, Ordering};
+
+/// How far the timestamp field is shifted left within a 64-bit snowflake.
+const TIMESTAMP_SHIFT_64: u32 = 22;
+/// How far the node ID field is shifted left within a 64-bit snowflake.
+const NODE_ID_SHIFT_64: u32 = 12;
+/// The largest timestamp that fits in a 64-bit snowflake.
+const MAX_TIMESTAMP_64: u64 = (1 << 42) - 1;
+/// The largest node ID that fits in a 64-bit snowflake.
+const MAX_NODE_ID_64: u16 = (1 << 10) - 1;
+/// The largest counter that fits in a 64-bit snowflake.
+const MAX_COUNTER_64: u16 = (1 << 12) - 1;
+
+/// The counter for 64-bit snowflakes, separate from the one for 128-bit snowflakes.
+static COUNTER_64: AtomicU16 = AtomicU16::new(0);
+
+/// A compact 64-bit snowflake, for systems that only accept 64-bit integers.
+///
+/// This is its own format, unrelated to the 128-bit layout the rest of this crate uses:
+/// * Bits 0 to 41: milliseconds since an epoch chosen by the caller. Range of around 139 years.
+/// * Bits 42 to 51: the node this ID was generated on, from 0 to 1,023
+/// * Bits 52 to 63: internal 12-bit atomic counter, allowing up to 4,096 IDs per millisecond
+///
+/// There's no room for a model type or API version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Snowflake64(pub u64);
+
+impl Snowflake64 {
+    /// Packs a 64-bit snowflake from exactly the fields given.
+    ///
+    /// # Errors
+    /// Returns [`SnowflakeError::TimestampOverflow`] if `timestamp_ms` doesn't fit in 42 bits,
+    /// [`SnowflakeError::NodeIdOverflow`] if `node_id` doesn't fit in 10 bits, or
+    /// [`SnowflakeError::CounterOverflow`] if `counter` doesn't fit in 12 bits.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use ferrischat_snowflake_generator::Snowflake64;
+    /// let id = Snowflake64::from_parts(1_234, 5, 6).unwrap();
+    /// assert_eq!((id.timestamp_ms(), id.node_id(), id.counter()), (1_234, 5, 6));
+    /// ```
+    pub const fn from_parts(
+        timestamp_ms: u64,
+        node_id: u16,
+        counter: u16,
+    ) -> Result<Self, SnowflakeError> {
+        if timestamp_ms > MAX_TIMESTAMP_64 {
+            return Err(SnowflakeError::TimestampOverflow);
+        }
+        if node_id > MAX_NODE_ID_64 {
+            return Err(SnowflakeError::NodeIdOverflow);
+        }
+        if counter > MAX_COUNTER_64 {
+            return Err(SnowflakeError::CounterOverflow);
+        }
+        Ok(Self(
+            (timestamp_ms << TIMESTAMP_SHIFT_64)
+                | ((node_id as u64) << NODE_ID_SHIFT_64)
+                | counter as u64,
+        ))
+    }
+
+    /// Returns the timestamp of this snowflake, in milliseconds since its epoch.
+    #[inline]
+    #[must_use]
+    pub const fn timestamp_ms(self) -> u64 {
+        self.0 >> TIMESTAMP_SHIFT_64
+    }
+
+    /// Returns the node this snowflake was generated on.
+    #[inline]
+    #[must_use]
+    pub const fn node_id(self) -> u16 {
+        ((self.0 >> NODE_ID_SHIFT_64) as u16) & MAX_NODE_ID_64
+    }
+
+    /// Returns the counter of this snowflake.
+    #[inline]
+    #[must_use]
+    pub const fn counter(self) -> u16 {
+        (self.0 as u16) & MAX_COUNTER_64
+    }
+}
+
+impl fmt::Display for Snowflake64 {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl From<Snowflake64> for u64 {
+    #[inline]
+    fn from(snowflake: Snowflake64) -> Self {
+        snowflake.0
+    }
+}
+
+/// Generates a [`Snowflake64`] for a node, counting milliseconds since `epoch_unix_ms`
+/// milliseconds since the Unix Epoch.
+///
+/// The clock is read like [`generate_snowflake`](crate::generate_snowflake) reads it, with the
+/// same rollback checks. The 12-bit counter wraps after 4,096 snowflakes in one millisecond.
+///
+/// # Errors
+/// Returns [`SnowflakeError::NodeIdOverflow`] if `node_id` doesn't fit in 10 bits,
+/// [`SnowflakeError::TimestampUnderflow`] if the epoch is in the future, or
+/// [`SnowflakeError::TimestampOverflow`] if it's more than 42 bits of milliseconds in the past.
+///
+/// # Panics
+/// Panics if the current time is behind the Unix Epoch.
+///
+/// # Examples
+/// ```rust
+/// use ferrischat_snowflake_generator::{generate_snowflake64, FERRIS_EPOCH};
+/// let id = generate_snowflake64(42, FERRIS_EPOCH).unwrap();
+/// assert_eq!(id.node_id(), 42);
+/// ```
+pub fn generate_snowflake64(
+    node_id: u16,
+    epoch_unix_ms: u128,
+) -> Result<Snowflake64, SnowflakeError> {
+    if node_id > MAX_NODE_ID_64 {
+        return Err(SnowflakeError::NodeIdOverflow);
+    }
+    let timestamp = (current_time() + FERRIS_EPOCH)
+        .checked_sub(epoch_unix_ms)
+        .ok_or(SnowflakeError::TimestampUnderflow)?;
+    let timestamp = u64::try_from(timestamp).map_err(|_| SnowflakeError::TimestampOverflow)?;
+    // fetch_add wraps on overflow, and 4,096 divides 65,536, so the masked counter wraps evenly
+    let counter = COUNTER_64.fetch_add(1, Ordering::Relaxed) & MAX_COUNTER_64;
+    Snowflake64::from_parts(timestamp, node_id, counter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::with_frozen_time;
+
+    #[test]
+    fn fields_fill_64_bits_without_overlapping() {
+        let all = Snowflake64::from_parts(MAX_TIMESTAMP_64, MAX_NODE_ID_64, MAX_COUNTER_64);
+        assert_eq!(all, Ok(Snowflake64(u64::MAX)));
+        let fields = [
+            Snowflake64::from_parts(MAX_TIMESTAMP_64, 0, 0).unwrap().0,
+            Snowflake64::from_parts(0, MAX_NODE_ID_64, 0).unwrap().0,
+            Snowflake64::from_parts(0, 0, MAX_COUNTER_64).unwrap().0,
+        ];
+        assert_eq!(fields, [!0 << 22, 0x3FF << 12, 0xFFF]);
+        assert_eq!(fields.iter().sum::<u64>(), u64::MAX);
+
+        let id = Snowflake64::from_parts(MAX_TIMESTAMP_64, MAX_NODE_ID_64, MAX_COUNTER_64).unwrap();
+        assert_eq!(id.timestamp_ms(), MAX_TIMESTAMP_64);
+        assert_eq!(id.node_id(), MAX_NODE_ID_64);
+        assert_eq!(id.counter(), MAX_COUNTER_64);
+    }
+
+    #[test]
+    fn oversized_fields_error() {
+        assert_eq!(
+            Snowflake64::from_parts(MAX_TIMESTAMP_64 + 1, 0, 0),
+            Err(SnowflakeError::TimestampOverflow)
+        );
+        assert_eq!(
+            Snowflake64::from_parts(0, MAX_NODE_ID_64 + 1, 0),
+            Err(SnowflakeError::NodeIdOverflow)
+        );
+        assert_eq!(
+            Snowflake64::from_parts(0, 0, MAX_COUNTER_64 + 1),
+            Err(SnowflakeError::CounterOverflow)
+        );
+        assert_eq!(
+            generate_snowflake64(MAX_NODE_ID_64 + 1, FERRIS_EPOCH),
+            Err(SnowflakeError::NodeIdOverflow)
+        );
+    }
+
+    #[test]
+    fn generation_counts_from_the_epoch() {
+        const COMPANY_EPOCH: u128 = FERRIS_EPOCH + 1_000;
+        let (id_1, id_2) = with_frozen_time(1_234, || {
+            (
+                generate_snowflake64(7, COMPANY_EPOCH).unwrap(),
+                generate_snowflake64(7, COMPANY_EPOCH).unwrap(),
+            )
+        });
+        assert_ne!(id_1, id_2);
+        assert_eq!(id_1.timestamp_ms(), 234);
+        assert_eq!(id_2.timestamp_ms(), 234);
+        assert_eq!(id_1.node_id(), 7);
+        assert_eq!(u64::from(id_1), id_1.0);
+
+        assert_eq!(
+            with_frozen_time(999, || generate_snowflake64(7, COMPANY_EPOCH)),
+            Err(SnowflakeError::TimestampUnderflow)
+        );
+        assert_eq!(
+            with_frozen_time(MAX_TIMESTAMP_64 as u128 + 1_001, || {
+                generate_snowflake64(7, COMPANY_EPOCH)
+            }),
+            Err(SnowflakeError::TimestampOverflow)
+        );
+    }
+}
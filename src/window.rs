@@ -0,0 +1,52 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+/// Counts how many snowflakes a generator has stamped with the current millisecond.
+///
+/// The count is exact for a single thread, and can be off by the handful of snowflakes that race
+/// a millisecond boundary when generating from many threads at once.
+pub(crate) struct Window {
+    /// The millisecond being counted.
+    timestamp: AtomicU64,
+    /// How many snowflakes have been stamped with `timestamp`.
+    count: AtomicU32,
+}
+
+impl Window {
+    pub(crate) const fn new() -> Self {
+        Self {
+            timestamp: AtomicU64::new(0),
+            count: AtomicU32::new(0),
+        }
+    }
+
+    /// Records a snowflake stamped with `timestamp`, returning how many snowflakes (including
+    /// this one) have been stamped with it.
+    #[inline]
+    pub(crate) fn record(&self, timestamp: u128) -> u32 {
+        let timestamp = timestamp as u64;
+        let mut current = self.timestamp.load(Ordering::Acquire);
+        // keep trying while the timestamp is ahead, so `latest` never misses a later millisecond
+        while timestamp > current {
+            match self.timestamp.compare_exchange_weak(
+                current,
+                timestamp,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => {
+                    self.count.store(1, Ordering::Release);
+                    return 1;
+                }
+                Err(actual) => current = actual,
+            }
+        }
+        self.count.fetch_add(1, Ordering::AcqRel).saturating_add(1)
+    }
+
+    /// Returns the latest millisecond a snowflake has been recorded as stamped with, or 0 if
+    /// none has.
+    #[inline]
+    pub(crate) fn latest(&self) -> u128 {
+        self.timestamp.load(Ordering::Acquire) as u128
+    }
+}
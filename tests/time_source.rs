@@ -0,0 +1,23 @@
+//! `set_time_source` replaces the clock for the whole process, so this runs in its own test
+//! binary, away from every test that reads the system clock.
+#![cfg(feature = "wasm")]
+
+use ferrischat_snowflake_generator::{
+    generate_snowflake, get_epoch_time, set_time_source, timestamp_of, FERRIS_EPOCH,
+};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Far enough past the Ferris Epoch that `SystemTime` can't have reached it yet.
+const FAKE_START: u128 = 1 << 40;
+
+#[test]
+fn time_source_replaces_system_time() {
+    static FAKE_TICKS: AtomicU64 = AtomicU64::new(0);
+
+    set_time_source(|| {
+        FERRIS_EPOCH + FAKE_START + FAKE_TICKS.fetch_add(1, Ordering::Relaxed) as u128
+    });
+    let snowflake = generate_snowflake::<0>(0, 0);
+    assert!(timestamp_of(snowflake) >= FAKE_START);
+    assert!(get_epoch_time() > timestamp_of(snowflake));
+}